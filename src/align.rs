@@ -0,0 +1,357 @@
+//! Word timing for chunks whose TTS engine does not report any.
+//!
+//! The heuristic aligner spreads a chunk's speech over its words by length,
+//! with fixed pauses at punctuation. The energy aligner instead finds the
+//! real pauses in the chunk's audio, snaps sentence and comma boundaries to
+//! them and only distributes words within each voiced stretch, so timing
+//! errors cannot accumulate across a long chunk.
+
+use crate::audio::{self, SilenceOptions, SilentRegion};
+use crate::tts::WordTiming;
+use regex::Regex;
+use std::sync::LazyLock;
+use tracing::debug;
+
+/// Assumed pause after a comma in heuristic alignment, in seconds.
+const COMMA_PAUSE: f64 = 0.2;
+/// Assumed pause after a sentence in heuristic alignment, in seconds.
+const SENTENCE_END_PAUSE: f64 = 0.4;
+/// Exponent applied to word length when sharing time between words.
+const WORD_WEIGHT_EXPONENT: f64 = 0.75;
+/// Shortest gap in the audio treated as a possible phrase boundary, in milliseconds.
+const MIN_BOUNDARY_PAUSE_MS: f64 = 80.0;
+/// How far a boundary may be from its expected time and still snap to a pause,
+/// as a fraction of the speech left in the chunk.
+const SNAP_TOLERANCE: f64 = 0.25;
+
+static TOKEN_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(\w[\w'-]*)|([,.!?;])").unwrap());
+
+/// How subtitle words are timed when the engine gives no word timings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Alignment {
+    /// Snap phrase boundaries to pauses detected in the audio
+    Energy,
+    /// Spread words by length with fixed punctuation pauses
+    Heuristic,
+}
+
+/// Punctuation that ends a phrase.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Boundary {
+    /// A comma or semicolon
+    Comma,
+    /// A full stop, exclamation mark or question mark
+    SentenceEnd,
+}
+
+/// Words between two punctuation marks.
+#[derive(Debug, Default)]
+struct Phrase<'a> {
+    words: Vec<&'a str>,
+    /// Punctuation following the phrase, if any
+    boundary: Option<Boundary>,
+}
+
+impl Phrase<'_> {
+    fn weight(&self) -> f64 {
+        self.words.iter().map(|w| word_weight(w)).sum()
+    }
+}
+
+fn word_weight(word: &str) -> f64 {
+    (word.chars().count() as f64).powf(WORD_WEIGHT_EXPONENT)
+}
+
+/// Splits text into phrases at commas, semicolons and sentence ends.
+fn phrases(text: &str) -> Vec<Phrase<'_>> {
+    let mut phrases = vec![Phrase::default()];
+    for token in TOKEN_REGEX.find_iter(text).map(|m| m.as_str()) {
+        let boundary = match token {
+            "," | ";" => Boundary::Comma,
+            "." | "!" | "?" => Boundary::SentenceEnd,
+            word => {
+                let current = phrases.last_mut().unwrap();
+                if current.boundary.is_some() {
+                    phrases.push(Phrase::default());
+                }
+                phrases.last_mut().unwrap().words.push(word);
+                continue;
+            }
+        };
+        let current = phrases.last_mut().unwrap();
+        current.boundary = current.boundary.max(Some(boundary));
+    }
+    phrases.retain(|p| !p.words.is_empty());
+    phrases
+}
+
+/// Finds the punctuation following each word of a text.
+///
+/// # Arguments
+/// * `text` - Text to scan
+///
+/// # Returns
+/// * `Vec<Option<Boundary>>` - One entry per word, with the strongest punctuation after it
+pub fn word_boundaries(text: &str) -> Vec<Option<Boundary>> {
+    phrases(text)
+        .iter()
+        .flat_map(|p| {
+            let last = p.words.len() - 1;
+            (0..p.words.len()).map(move |i| if i == last { p.boundary } else { None })
+        })
+        .collect()
+}
+
+/// Distributes `[start, end]` over the words of `phrases` by word length.
+fn spread_words(phrases: &[Phrase], start: f64, end: f64, timings: &mut Vec<WordTiming>) {
+    let total: f64 = phrases.iter().map(Phrase::weight).sum();
+    let mut t = start;
+    for word in phrases.iter().flat_map(|p| &p.words) {
+        let duration = if total > 0.0 {
+            (end - start).max(0.0) * word_weight(word) / total
+        } else {
+            0.0
+        };
+        timings.push(WordTiming {
+            word: word.to_string(),
+            start: t,
+            end: t + duration,
+        });
+        t += duration;
+    }
+}
+
+/// Times words by length with fixed pauses at punctuation.
+///
+/// # Arguments
+/// * `text` - Chunk text
+/// * `start` - When speech starts in the chunk, in seconds
+/// * `end` - When speech ends in the chunk, in seconds
+///
+/// # Returns
+/// * `Vec<WordTiming>` - Word timings relative to the start of the chunk
+pub fn heuristic_timings(text: &str, start: f64, end: f64) -> Vec<WordTiming> {
+    let phrases = phrases(text);
+    let pause = |p: &Phrase| match p.boundary {
+        Some(Boundary::Comma) => COMMA_PAUSE,
+        Some(Boundary::SentenceEnd) => SENTENCE_END_PAUSE,
+        None => 0.0,
+    };
+    let total_pause: f64 = phrases.iter().map(pause).sum();
+    let total_weight: f64 = phrases.iter().map(Phrase::weight).sum();
+    let word_time = (end - start - total_pause).max(0.0);
+
+    let mut timings = Vec::new();
+    let mut t = start;
+    for phrase in &phrases {
+        let phrase_end = if total_weight > 0.0 {
+            t + word_time * phrase.weight() / total_weight
+        } else {
+            t
+        };
+        spread_words(std::slice::from_ref(phrase), t, phrase_end, &mut timings);
+        t = phrase_end + pause(phrase);
+    }
+    timings
+}
+
+/// Times words by snapping phrase boundaries to pauses found in the audio.
+///
+/// Each boundary is expected where its share of the remaining words would put
+/// it, and snaps to the nearest unused pause within tolerance. Unmatched
+/// commas are treated as spoken without a pause; an unmatched sentence end
+/// makes the alignment ambiguous.
+///
+/// # Arguments
+/// * `text` - Chunk text
+/// * `start` - When speech starts in the chunk, in seconds
+/// * `end` - When speech ends in the chunk, in seconds
+/// * `pauses` - Silent regions of the chunk
+///
+/// # Returns
+/// * `Some(Vec<WordTiming>)` - Word timings relative to the start of the chunk
+/// * `None` - If the pauses do not fit the punctuation
+pub fn energy_timings(
+    text: &str,
+    start: f64,
+    end: f64,
+    pauses: &[SilentRegion],
+) -> Option<Vec<WordTiming>> {
+    let phrases = phrases(text);
+    let pauses: Vec<&SilentRegion> = pauses
+        .iter()
+        .filter(|p| p.start > start && p.end < end)
+        .collect();
+
+    let mut timings = Vec::new();
+    let (mut segment_start, mut first_phrase, mut next_pause) = (start, 0, 0);
+    for (i, phrase) in phrases
+        .iter()
+        .enumerate()
+        .take(phrases.len().saturating_sub(1))
+    {
+        let Some(boundary) = phrase.boundary else {
+            continue;
+        };
+        let remaining: f64 = phrases[first_phrase..].iter().map(Phrase::weight).sum();
+        let done: f64 = phrases[first_phrase..=i].iter().map(Phrase::weight).sum();
+        if remaining <= 0.0 {
+            return None;
+        }
+        let span = end - segment_start;
+        let expected = segment_start + span * done / remaining;
+        let snapped = pauses
+            .iter()
+            .enumerate()
+            .skip(next_pause)
+            .map(|(j, p)| (j, p, ((p.start + p.end) / 2.0 - expected).abs()))
+            .filter(|(_, _, distance)| *distance <= span * SNAP_TOLERANCE)
+            .min_by(|a, b| a.2.total_cmp(&b.2));
+        match snapped {
+            Some((j, pause, _)) => {
+                spread_words(
+                    &phrases[first_phrase..=i],
+                    segment_start,
+                    pause.start,
+                    &mut timings,
+                );
+                segment_start = pause.end;
+                first_phrase = i + 1;
+                next_pause = j + 1;
+            }
+            None if boundary == Boundary::SentenceEnd => return None,
+            None => {}
+        }
+    }
+    if segment_start >= end {
+        return None;
+    }
+    spread_words(&phrases[first_phrase..], segment_start, end, &mut timings);
+    Some(timings)
+}
+
+/// Computes word timings for a synthesized chunk.
+///
+/// # Arguments
+/// * `path` - The chunk's WAV file
+/// * `text` - Text spoken in the chunk
+/// * `alignment` - Which aligner to use
+/// * `silence` - How silence is detected in the chunk
+///
+/// # Returns
+/// * `Ok(Vec<WordTiming>)` - Word timings relative to the start of the chunk
+/// * `Err` - If the WAV file cannot be analyzed
+pub fn align_chunk(
+    path: &str,
+    text: &str,
+    alignment: Alignment,
+    silence: &SilenceOptions,
+) -> anyhow::Result<Vec<WordTiming>> {
+    let duration = audio::wav_duration_seconds(path)?;
+    let leading = audio::detect_leading_silence(path, silence)?;
+    let trailing = audio::detect_trailing_silence(path, silence)?;
+    let (start, end) = if leading < duration - trailing {
+        (leading, duration - trailing)
+    } else {
+        (0.0, duration)
+    };
+
+    if alignment == Alignment::Energy {
+        let pauses = audio::detect_silence(
+            path,
+            &SilenceOptions {
+                min_silence_ms: MIN_BOUNDARY_PAUSE_MS,
+                ..*silence
+            },
+        )?;
+        match energy_timings(text, start, end, &pauses) {
+            Some(timings) => return Ok(timings),
+            None => debug!(
+                "Pauses in {} do not match its punctuation; using heuristic timing",
+                path
+            ),
+        }
+    }
+    Ok(heuristic_timings(text, start, end))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Flattens timings into comparable `(word, start, end)` tuples.
+    fn spans(timings: &[WordTiming]) -> Vec<(&str, f64, f64)> {
+        timings
+            .iter()
+            .map(|t| (t.word.as_str(), t.start, t.end))
+            .collect()
+    }
+
+    #[test]
+    fn sentence_ends_snap_to_pauses() {
+        let pauses = [SilentRegion {
+            start: 1.3,
+            end: 1.7,
+        }];
+        let timings = energy_timings("One two three. Four five six.", 0.0, 3.0, &pauses).unwrap();
+        let spans = spans(&timings);
+        assert_eq!(spans.len(), 6);
+        // The first sentence fills the speech before the pause, the second the speech after it.
+        assert_eq!(spans[0].1, 0.0);
+        assert!((spans[2].2 - 1.3).abs() < 1e-9, "{spans:?}");
+        assert!((spans[3].1 - 1.7).abs() < 1e-9, "{spans:?}");
+        assert!((spans[5].2 - 3.0).abs() < 1e-9, "{spans:?}");
+        for pair in spans.windows(2) {
+            assert!(pair[0].2 <= pair[1].1 + 1e-9, "{spans:?}");
+        }
+    }
+
+    #[test]
+    fn unmatched_comma_is_spoken_through() {
+        let pauses = [SilentRegion {
+            start: 1.3,
+            end: 1.7,
+        }];
+        let timings = energy_timings("One, two three. Four five six.", 0.0, 3.0, &pauses).unwrap();
+        assert_eq!(timings.len(), 6);
+        assert!((timings[2].end - 1.3).abs() < 1e-9);
+    }
+
+    #[test]
+    fn sentence_end_without_a_pause_is_ambiguous() {
+        assert!(energy_timings("One two three. Four five six.", 0.0, 3.0, &[]).is_none());
+        // A pause far from where the sentence should end does not count either.
+        let pauses = [SilentRegion {
+            start: 0.1,
+            end: 0.2,
+        }];
+        assert!(energy_timings("One two three. Four five six.", 0.0, 3.0, &pauses).is_none());
+    }
+
+    #[test]
+    fn chunk_without_pauses_falls_back_to_heuristic_timing() {
+        let path = std::env::temp_dir()
+            .join(format!("align_fallback_{}.wav", std::process::id()))
+            .to_string_lossy()
+            .into_owned();
+        let spec = hound::WavSpec {
+            channels: 1,
+            sample_rate: 16_000,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let mut writer = hound::WavWriter::create(&path, spec).unwrap();
+        for n in 0..32_000 {
+            let s = (n as f32 * 440.0 * std::f32::consts::TAU / 16_000.0).sin();
+            writer.write_sample((s * 16_000.0) as i16).unwrap();
+        }
+        writer.finalize().unwrap();
+
+        let text = "One two three. Four five six.";
+        let timings =
+            align_chunk(&path, text, Alignment::Energy, &SilenceOptions::default()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(spans(&timings), spans(&heuristic_timings(text, 0.0, 2.0)));
+    }
+}
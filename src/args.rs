@@ -1,6 +1,11 @@
 //! Command-line argument definitions for the Reddit stories video generator.
 
-use clap::Parser;
+use crate::align::Alignment;
+use crate::background::BackgroundPick;
+use crate::config::SpeakerRef;
+use crate::subtitle::{CaptionGrouping, SubtitleFormat};
+use crate::tts::TtsEngineKind;
+use clap::{Parser, Subcommand};
 
 /// Command-line arguments for configuring the Reddit stories video generation process.
 ///
@@ -8,21 +13,322 @@ use clap::Parser;
 /// file paths, TTS settings, and text processing options.
 #[derive(Parser, Debug)]
 pub struct Args {
+    /// Maintenance command to run instead of generating a video
+    #[command(subcommand)]
+    pub command: Option<Commands>,
+
     /// The subreddit to fetch stories from (without the 'r/' prefix)
     #[clap(long, default_value = "AITAH")]
     pub subreddit: String,
 
-    /// Path to the background video file
+    /// Path to the background video file, or a directory of clips to pick from
     #[clap(long, default_value = "./res/bg.mp4")]
     pub background: String,
 
+    /// Only pick background clips with this tag from the library manifest (repeatable)
+    #[clap(long)]
+    pub background_tag: Vec<String>,
+
+    /// How to pick a clip from a background directory
+    #[clap(long, value_enum, default_value_t = BackgroundPick::Lru)]
+    pub background_pick: BackgroundPick,
+
+    /// Path to the JSON file recording when each background clip was last used
+    #[clap(long, default_value = "./config/background_usage.json")]
+    pub background_usage: String,
+
+    /// Seed for the random background clip and start offset; a random seed is used and logged if omitted
+    #[clap(long)]
+    pub background_seed: Option<u64>,
+
     /// Output path for the generated video file
     #[clap(long, default_value = "out.mp4")]
     pub out: String,
 
+    /// Split stories longer than this many seconds into parts (`out_part1.mp4`, ...)
+    #[clap(long)]
+    pub max_duration: Option<f64>,
+
+    /// Announcement spoken and captioned at the start of each part; `{n}` is the part number
+    #[clap(long, default_value = "Part {n}")]
+    pub part_announcement: String,
+
+    /// Output profile: `short` (1080x1920), `landscape` (1920x1080), `square` (1080x1080)
+    /// or one defined in the config file [default: short]
+    #[clap(long)]
+    pub output_profile: Option<String>,
+
+    /// Output frame size as WIDTHxHEIGHT; the background is scaled and cropped to fill it
+    #[clap(long)]
+    pub resolution: Option<String>,
+
+    /// Output frame rate
+    #[clap(long)]
+    pub fps: Option<u32>,
+
+    /// x264 constant rate factor (0-51, lower is better quality)
+    #[clap(long)]
+    pub crf: Option<u8>,
+
+    /// x264 speed preset (ultrafast to veryslow)
+    #[clap(long)]
+    pub x264_preset: Option<String>,
+
+    /// AAC audio bitrate, e.g. 192k
+    #[clap(long)]
+    pub audio_bitrate: Option<String>,
+
+    /// Output pixel format, e.g. yuv420p
+    #[clap(long)]
+    pub pix_fmt: Option<String>,
+
+    /// Path to the JSON configuration file (optional; CLI options override it)
+    #[clap(long, default_value = "./config/config.json")]
+    pub config: String,
+
+    /// TTS engine to use [default: piper]
+    #[clap(long, value_enum)]
+    pub tts_engine: Option<TtsEngineKind>,
+
     /// Path to the Piper TTS model file (.onnx format)
-    #[clap(long, default_value = "./tts/en_US-hfc_male-medium.onnx")]
-    pub piper_model: String,
+    /// [default: ./tts/en_US-hfc_male-medium.onnx]
+    #[clap(long)]
+    pub piper_model: Option<String>,
+
+    /// Pass lexicon `[[phonemes]]` to Piper (`true`/`false`)
+    /// [default: if the model's `.onnx.json` uses eSpeak phonemes]
+    #[clap(long)]
+    pub piper_phonemes: Option<bool>,
+
+    /// Voice profile from the configuration file used for narration
+    #[clap(long)]
+    pub voice_profile: Option<String>,
+
+    /// Voice profile for the post title [default: narrator voice]
+    #[clap(long)]
+    pub title_voice: Option<String>,
+
+    /// Seconds of silence between the title and the story body
+    #[clap(long, default_value_t = 0.6)]
+    pub title_pause: f64,
+
+    /// Overlay a Reddit-style title card while the title is narrated
+    #[clap(long)]
+    pub title_card: bool,
+
+    /// Title card theme: `light`, `dark` or one defined in the config file [default: light]
+    #[clap(long)]
+    pub title_card_theme: Option<String>,
+
+    /// Font file for the title card [default: the system sans-serif font]
+    #[clap(long)]
+    pub title_card_font: Option<String>,
+
+    /// Subreddit icon image for the title card [default: a plain `r/` badge]
+    #[clap(long)]
+    pub title_card_icon: Option<String>,
+
+    /// Voice profile for quoted dialogue; enables multi-voice narration
+    #[clap(long)]
+    pub dialogue_voice: Option<String>,
+
+    /// Piper speaker id or name for multi-speaker models
+    #[clap(long)]
+    pub speaker: Option<SpeakerRef>,
+
+    /// Piper phoneme length multiplier; larger values speak more slowly
+    #[clap(long)]
+    pub length_scale: Option<f32>,
+
+    /// Piper generator noise
+    #[clap(long)]
+    pub noise_scale: Option<f32>,
+
+    /// Piper phoneme width noise
+    #[clap(long)]
+    pub noise_w: Option<f32>,
+
+    /// Seconds of silence Piper inserts after each sentence
+    #[clap(long)]
+    pub sentence_silence: Option<f32>,
+
+    /// Maximum number of chunks synthesized concurrently (also the number of
+    /// persistent Piper processes) [default: 4]
+    #[clap(long)]
+    pub tts_jobs: Option<usize>,
+
+    /// eSpeak NG voice name [default: en-us]
+    #[clap(long)]
+    pub espeak_voice: Option<String>,
+
+    /// Command template for the `command` TTS engine, e.g. `mytts --out {output}`
+    #[clap(long)]
+    pub tts_command: Option<String>,
+
+    /// Extra attempts per chunk after a failed synthesis before it is re-split
+    #[clap(long, default_value_t = crate::tts::DEFAULT_TTS_RETRIES)]
+    pub tts_retries: usize,
+
+    /// Render the video without chunks that still fail after all retries
+    #[clap(long)]
+    pub skip_failed_chunks: bool,
+
+    /// Directory of the content-addressed TTS chunk cache
+    #[clap(long, default_value = "./cache/tts")]
+    pub tts_cache_dir: String,
+
+    /// Size cap of the TTS cache in megabytes; least recently used chunks are evicted
+    #[clap(long, default_value_t = 1024)]
+    pub tts_cache_max_mb: u64,
+
+    /// Always synthesize chunks instead of using the TTS cache
+    #[clap(long)]
+    pub no_tts_cache: bool,
+
+    /// Seconds of silence inserted between narration chunks
+    #[clap(long, default_value_t = 0.0)]
+    pub chunk_gap: f64,
+
+    /// RMS level in dBFS below which audio counts as silence
+    #[clap(long, default_value_t = -40.0, allow_negative_numbers = true)]
+    pub silence_threshold: f64,
+
+    /// Subtitle format burned into the video
+    #[clap(long, value_enum, default_value_t = SubtitleFormat::Srt)]
+    pub subtitle_format: SubtitleFormat,
+
+    /// How words are grouped into captions [default: word for SRT, words for ASS]
+    #[clap(long, value_enum)]
+    pub caption_grouping: Option<CaptionGrouping>,
+
+    /// Most words per caption when grouping by words
+    #[clap(long, default_value_t = 3)]
+    pub caption_words: usize,
+
+    /// Most characters per caption when grouping by phrase
+    #[clap(long, default_value_t = 32)]
+    pub caption_chars: usize,
+
+    /// Shortest time a caption stays on screen in seconds, if the next caption allows it
+    #[clap(long, default_value_t = 0.3)]
+    pub min_caption_duration: f64,
+
+    /// Subtitle style preset: classic, bold, minimal or one from the configuration file
+    /// [default: classic for SRT, bold for ASS]
+    #[clap(long)]
+    pub subtitle_preset: Option<String>,
+
+    /// Subtitle font family
+    #[clap(long)]
+    pub subtitle_font: Option<String>,
+
+    /// Subtitle font file (.ttf/.otf); the family defaults to the file name
+    #[clap(long)]
+    pub subtitle_font_file: Option<String>,
+
+    /// Subtitle font size in pixels of the frame scaled to 1920 pixels tall
+    #[clap(long)]
+    pub subtitle_size: Option<f64>,
+
+    /// Subtitle text colour as `#RRGGBB`
+    #[clap(long)]
+    pub subtitle_colour: Option<String>,
+
+    /// Subtitle outline colour as `#RRGGBB`
+    #[clap(long)]
+    pub subtitle_outline_colour: Option<String>,
+
+    /// Subtitle outline width in pixels
+    #[clap(long)]
+    pub subtitle_outline: Option<f64>,
+
+    /// Subtitle shadow distance in pixels
+    #[clap(long)]
+    pub subtitle_shadow: Option<f64>,
+
+    /// Subtitle position as a numpad digit (1 = bottom left, 5 = centre, 9 = top right)
+    #[clap(long)]
+    pub subtitle_position: Option<u8>,
+
+    /// Left and right subtitle margins in pixels
+    #[clap(long)]
+    pub subtitle_margin_h: Option<u32>,
+
+    /// Vertical subtitle margin in pixels
+    #[clap(long)]
+    pub subtitle_margin_v: Option<u32>,
+
+    /// Show burned-in captions in upper case
+    #[clap(long)]
+    pub subtitle_uppercase: bool,
+
+    /// Most lines per caption; longer captions are split into several
+    #[clap(long)]
+    pub subtitle_max_lines: Option<usize>,
+
+    /// Ignore the keyword emphasis configured in the `subtitles.emphasis` config section
+    #[clap(long)]
+    pub no_emphasis: bool,
+
+    /// File with a `[V4+ Styles]` section (defining `Default`) for ASS subtitles
+    #[clap(long)]
+    pub ass_style: Option<String>,
+
+    /// Colour of the word being spoken in ASS subtitles, as `#RRGGBB`
+    #[clap(long, default_value = "#FFFF00")]
+    pub highlight_colour: String,
+
+    /// How subtitle words are timed when the TTS engine reports no word timings
+    #[clap(long, value_enum, default_value_t = Alignment::Energy)]
+    pub subtitle_alignment: Alignment,
+
+    /// Seconds of silence kept before and after the speech in each chunk
+    #[clap(long, default_value_t = 0.1)]
+    pub trim_pad: f64,
+
+    /// Shorten pauses inside a chunk that are longer than this many seconds
+    #[clap(long)]
+    pub max_pause: Option<f64>,
+
+    /// Keep the silence the TTS engine produced around each chunk
+    #[clap(long)]
+    pub no_trim: bool,
+
+    /// Background music file, or a directory to pick a random track from
+    #[clap(long)]
+    pub music: Option<String>,
+
+    /// Music loudness relative to the narration in dB
+    #[clap(long, default_value_t = -18.0, allow_negative_numbers = true)]
+    pub music_volume: f64,
+
+    /// How far the music is turned down while the narration speaks, in dB
+    #[clap(long, default_value_t = 12.0)]
+    pub music_duck: f64,
+
+    /// Music fade-in length in seconds
+    #[clap(long, default_value_t = 2.0)]
+    pub music_fade_in: f64,
+
+    /// Music fade-out length in seconds
+    #[clap(long, default_value_t = 3.0)]
+    pub music_fade_out: f64,
+
+    /// Integrated loudness target for the narration in LUFS, measured before music is mixed in
+    #[clap(long, default_value_t = -14.0, allow_negative_numbers = true)]
+    pub target_lufs: f64,
+
+    /// True-peak ceiling for the final audio in dBTP, including any music
+    #[clap(long, default_value_t = -1.0, allow_negative_numbers = true)]
+    pub true_peak: f64,
+
+    /// Keep the narration at the TTS engine's original level
+    #[clap(long)]
+    pub no_normalize: bool,
+
+    /// Pronunciation lexicon file (`word = respelling` or `word = [[phonemes]]` per line)
+    #[clap(long, default_value = "./config/lexicon.txt")]
+    pub lexicon: String,
 
     /// Maximum number of posts to try before giving up
     #[clap(long, default_value_t = usize::MAX)]
@@ -36,3 +342,60 @@ pub struct Args {
     #[clap(long, default_value_t = 1000)]
     pub min_chars: usize,
 }
+
+/// Maintenance subcommands.
+#[derive(Subcommand, Debug)]
+pub enum Commands {
+    /// Evict least recently used entries from the TTS cache
+    EvictCache {
+        /// Target cache size in megabytes [default: --tts-cache-max-mb]; 0 clears the cache
+        #[clap(long)]
+        max_mb: Option<u64>,
+    },
+    /// Edit, re-time or check subtitle files of a finished render
+    Subtitles {
+        #[command(subcommand)]
+        action: SubtitleCommand,
+    },
+}
+
+/// Subtitle file tools.
+#[derive(Subcommand, Debug)]
+pub enum SubtitleCommand {
+    /// Burn an edited SRT or VTT file onto the background with a render's narration
+    ///
+    /// Subtitles already burned into a render cannot be removed, so the video
+    /// is taken from --background again. Subtitle format and style options
+    /// apply as for a normal run.
+    Burn {
+        /// SRT or VTT file to burn in
+        subtitles: String,
+        /// Rendered video whose audio is reused
+        #[clap(long)]
+        render: String,
+        /// Output path for the new video
+        #[clap(long)]
+        output: String,
+    },
+    /// Shift and scale the timings of an SRT or VTT file
+    Retime {
+        /// SRT or VTT file to read
+        input: String,
+        /// Output path; the format follows its extension (.srt or .vtt)
+        output: String,
+        /// Seconds added to every time, after scaling
+        #[clap(long, default_value_t = 0.0, allow_hyphen_values = true)]
+        shift: f64,
+        /// Factor every time is multiplied by
+        #[clap(long, default_value_t = 1.0)]
+        scale: f64,
+    },
+    /// Check that an SRT or VTT file is well formed and fits the audio
+    Validate {
+        /// SRT or VTT file to check
+        subtitles: String,
+        /// Combined narration WAV or rendered video to check the timings against
+        #[clap(long)]
+        audio: Option<String>,
+    },
+}
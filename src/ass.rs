@@ -0,0 +1,279 @@
+//! Advanced SubStation Alpha (ASS) subtitles with karaoke word highlighting.
+//!
+//! Each cue of a grouped track is one line. While a line is on screen it is
+//! redrawn for every word, with the word being spoken highlighted in a
+//! different colour and scale, giving the "current word pops" look used in
+//! short-form videos.
+
+use crate::emphasis::Emphasis;
+use crate::style::{ResolvedStyle, ass_colour};
+use crate::subtitle::Track;
+use std::fmt::Write as _;
+use std::fs;
+
+/// Size of the active word relative to the rest of the line, in percent.
+const HIGHLIGHT_SCALE: u32 = 115;
+
+/// Settings for the ASS writer.
+#[derive(Debug, Clone)]
+pub struct AssOptions {
+    /// `[V4+ Styles]` section defining the `Default` style
+    pub styles: String,
+    /// Colour of the word being spoken, as `#RRGGBB`
+    pub highlight_colour: String,
+    /// Keywords drawn with their own colour, size or emoji
+    pub emphasis: Emphasis,
+    /// Script resolution, matching the aspect ratio of the rendered video
+    pub play_res: (u32, u32),
+}
+
+impl AssOptions {
+    /// Builds writer options, reading the style section from a file if one is given.
+    ///
+    /// # Arguments
+    /// * `style_file` - Path to a file holding a `[V4+ Styles]` section with a `Default` style
+    /// * `style` - Subtitle style used when there is no style file
+    /// * `highlight_colour` - Colour of the word being spoken, as `#RRGGBB`
+    /// * `emphasis` - Keywords drawn with their own colour, size or emoji
+    ///
+    /// # Returns
+    /// * `Ok(AssOptions)` - The writer options
+    /// * `Err` - If the style file cannot be read or has no `[V4+ Styles]` section
+    pub fn load(
+        style_file: Option<&str>,
+        style: &ResolvedStyle,
+        highlight_colour: &str,
+        emphasis: Emphasis,
+    ) -> anyhow::Result<Self> {
+        let styles = match style_file {
+            Some(path) => {
+                let styles = fs::read_to_string(path)?;
+                anyhow::ensure!(
+                    styles.contains("[V4+ Styles]") && styles.contains("Style:"),
+                    "ASS style file {path} must contain a [V4+ Styles] section with a Style: line"
+                );
+                styles
+            }
+            None => style.ass_styles(),
+        };
+        anyhow::ensure!(
+            ass_colour(highlight_colour).is_some(),
+            "Highlight colour must be #RRGGBB, got {highlight_colour}"
+        );
+        Ok(Self {
+            styles,
+            highlight_colour: highlight_colour.to_string(),
+            emphasis,
+            play_res: style.play_res(),
+        })
+    }
+}
+
+/// Formats seconds as an ASS timestamp (H:MM:SS.cc).
+fn format_ass_time(seconds: f64) -> String {
+    let total_cs = (seconds.max(0.0) * 100.0).round() as u64;
+    let cs = total_cs % 100;
+    let total_sec = total_cs / 100;
+    format!(
+        "{}:{:02}:{:02}.{:02}",
+        total_sec / 3600,
+        total_sec / 60 % 60,
+        total_sec % 60,
+        cs
+    )
+}
+
+/// Replaces characters that ASS would read as override tags.
+fn escape_ass(text: &str) -> String {
+    text.replace('\\', "/").replace('{', "(").replace('}', ")")
+}
+
+/// Writes a subtitle track as an ASS file with karaoke highlighting.
+///
+/// # Arguments
+/// * `path` - Output path for the ASS file
+/// * `track` - Grouped subtitle track; each cue becomes one line
+/// * `options` - Styles and highlight colour
+///
+/// # Returns
+/// * `Ok(())` - If the file was successfully written
+/// * `Err` - If the file cannot be written
+pub fn write_ass(path: &str, track: &Track, options: &AssOptions) -> anyhow::Result<()> {
+    let highlight = ass_colour(&options.highlight_colour).unwrap_or_default();
+    let mut out = String::new();
+    writeln!(out, "[Script Info]")?;
+    writeln!(out, "ScriptType: v4.00+")?;
+    writeln!(out, "PlayResX: {}", options.play_res.0)?;
+    writeln!(out, "PlayResY: {}", options.play_res.1)?;
+    writeln!(out, "WrapStyle: 0")?;
+    writeln!(out, "ScaledBorderAndShadow: yes")?;
+    writeln!(out)?;
+    writeln!(out, "{}", options.styles.trim_end())?;
+    writeln!(out)?;
+    writeln!(out, "[Events]")?;
+    writeln!(
+        out,
+        "Format: Layer, Start, End, Style, Name, MarginL, MarginR, MarginV, Effect, Text"
+    )?;
+
+    for line in &track.cues {
+        let base = line.style.colour.as_deref().and_then(ass_colour);
+        for (active, word) in line.words.iter().enumerate() {
+            // Keep the line on screen until the next word starts.
+            let start = if active == 0 { line.start } else { word.start };
+            let end = line
+                .words
+                .get(active + 1)
+                .map_or(line.end, |next| next.start);
+            let mut text = String::new();
+            for (i, w) in line.words.iter().enumerate() {
+                if i > 0 {
+                    text.push(' ');
+                }
+                let keyword = options.emphasis.lookup(&w.text);
+                let scale = keyword.and_then(|k| k.scale).unwrap_or(100);
+                let colour = keyword
+                    .and_then(|k| k.colour.as_deref())
+                    .and_then(ass_colour)
+                    .or_else(|| base.clone());
+                if i == active {
+                    let scale = scale * HIGHLIGHT_SCALE / 100;
+                    write!(
+                        text,
+                        "{{\\c{highlight}\\fscx{scale}\\fscy{scale}}}{}{{\\r}}",
+                        escape_ass(&w.text)
+                    )?;
+                } else if colour.is_some() || scale != 100 {
+                    let colour = colour.map(|c| format!("\\c{c}")).unwrap_or_default();
+                    write!(
+                        text,
+                        "{{{colour}\\fscx{scale}\\fscy{scale}}}{}{{\\r}}",
+                        escape_ass(&w.text)
+                    )?;
+                } else {
+                    text.push_str(&escape_ass(&w.text));
+                }
+                if let Some(emoji) = keyword.and_then(|k| k.emoji.as_deref()) {
+                    write!(text, " {}", escape_ass(emoji))?;
+                }
+            }
+            writeln!(
+                out,
+                "Dialogue: 0,{},{},Default,{},0,0,0,,{}",
+                format_ass_time(start),
+                format_ass_time(end),
+                line.speaker.as_deref().unwrap_or("").replace(',', " "),
+                text
+            )?;
+        }
+    }
+    fs::write(path, out)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::emphasis::KeywordStyle;
+    use crate::subtitle::{Cue, CueStyle, CueWord};
+    use std::collections::HashMap;
+
+    /// A cue made of `(word, start, end)` triples.
+    fn cue(words: &[(&str, f64, f64)], speaker: Option<&str>, colour: Option<&str>) -> Cue {
+        Cue {
+            start: words[0].1,
+            end: words[words.len() - 1].2,
+            text: words.iter().map(|w| w.0).collect::<Vec<_>>().join(" "),
+            words: words
+                .iter()
+                .map(|&(text, start, end)| CueWord {
+                    text: text.to_string(),
+                    start,
+                    end,
+                    sentence: 0,
+                    boundary: None,
+                })
+                .collect(),
+            speaker: speaker.map(str::to_string),
+            style: CueStyle {
+                colour: colour.map(str::to_string),
+            },
+            chunk: 0,
+        }
+    }
+
+    #[test]
+    fn write_ass_snapshot() {
+        let emphasis = Emphasis::new(&HashMap::from([(
+            "nta".to_string(),
+            KeywordStyle {
+                colour: Some("#FF0000".to_string()),
+                scale: Some(120),
+                emoji: Some("🔥".to_string()),
+            },
+        )]))
+        .unwrap();
+        let style = ResolvedStyle {
+            font: "Arial".to_string(),
+            fonts_dir: None,
+            size: 96.0,
+            primary_colour: "#FFFFFF".to_string(),
+            outline_colour: "#000000".to_string(),
+            outline: 8.0,
+            shadow: 0.0,
+            bold: true,
+            position: 5,
+            margin_l: 60,
+            margin_r: 60,
+            margin_v: 0,
+            uppercase: false,
+            max_lines: 2,
+            play_res_x: 1080.0,
+        };
+        let options = AssOptions::load(None, &style, "#FFFF00", emphasis).unwrap();
+        let track = Track {
+            cues: vec![
+                cue(&[("You're", 0.5, 0.8), ("NTA.", 0.9, 1.3)], None, None),
+                cue(
+                    &[("Get", 2.0, 2.2), ("{out}", 2.25, 2.6)],
+                    Some("mom, angry"),
+                    Some("#FFD54F"),
+                ),
+            ],
+        };
+        let path = std::env::temp_dir().join(format!("ass_snapshot_{}.ass", std::process::id()));
+        let path = path.to_str().unwrap();
+        write_ass(path, &track, &options).unwrap();
+        let ass = fs::read_to_string(path).unwrap();
+        fs::remove_file(path).unwrap();
+        let expected = [
+            "[Script Info]",
+            "ScriptType: v4.00+",
+            "PlayResX: 1080",
+            "PlayResY: 1920",
+            "WrapStyle: 0",
+            "ScaledBorderAndShadow: yes",
+            "",
+            "[V4+ Styles]",
+            "Format: Name, Fontname, Fontsize, PrimaryColour, SecondaryColour, OutlineColour, \
+             BackColour, Bold, Italic, Underline, StrikeOut, ScaleX, ScaleY, Spacing, Angle, \
+             BorderStyle, Outline, Shadow, Alignment, MarginL, MarginR, MarginV, Encoding",
+            "Style: Default,Arial,96,&HFFFFFF&,&HFFFFFF&,&H000000&,&H00000000&,-1,0,0,0,100,100,\
+             0,0,1,8.0,0.0,5,60,60,0,1",
+            "",
+            "[Events]",
+            "Format: Layer, Start, End, Style, Name, MarginL, MarginR, MarginV, Effect, Text",
+            // Each word is highlighted from its own start until the next word starts.
+            "Dialogue: 0,0:00:00.50,0:00:00.90,Default,,0,0,0,,\
+             {\\c&H00FFFF&\\fscx115\\fscy115}You're{\\r} \
+             {\\c&H0000FF&\\fscx120\\fscy120}NTA.{\\r} 🔥",
+            "Dialogue: 0,0:00:00.90,0:00:01.30,Default,,0,0,0,,\
+             You're {\\c&H00FFFF&\\fscx138\\fscy138}NTA.{\\r} 🔥",
+            "Dialogue: 0,0:00:02.00,0:00:02.25,Default,mom  angry,0,0,0,,\
+             {\\c&H00FFFF&\\fscx115\\fscy115}Get{\\r} {\\c&H4FD5FF&\\fscx100\\fscy100}(out){\\r}",
+            "Dialogue: 0,0:00:02.25,0:00:02.60,Default,mom  angry,0,0,0,,\
+             {\\c&H4FD5FF&\\fscx100\\fscy100}Get{\\r} {\\c&H00FFFF&\\fscx115\\fscy115}(out){\\r}",
+        ];
+        assert_eq!(ass.lines().collect::<Vec<_>>(), expected);
+    }
+}
@@ -1,7 +1,123 @@
 //! Audio processing utilities for WAV file analysis.
 //!
 //! This module provides functions for analyzing WAV audio files, including
-//! detecting silence periods and calculating audio duration.
+//! detecting silence periods and calculating audio duration. Silence is
+//! measured as windowed RMS across all channels, so detection behaves the same
+//! for any sample format, bit depth and channel layout.
+
+/// Settings for silence analysis.
+#[derive(Debug, Clone, Copy)]
+pub struct SilenceOptions {
+    /// RMS level in dBFS below which a window counts as silence
+    pub threshold_db: f64,
+    /// Length of each RMS analysis window in milliseconds
+    pub window_ms: f64,
+    /// Silent stretches shorter than this many milliseconds are ignored
+    pub min_silence_ms: f64,
+}
+
+impl Default for SilenceOptions {
+    fn default() -> Self {
+        Self {
+            threshold_db: -40.0,
+            window_ms: 10.0,
+            min_silence_ms: 50.0,
+        }
+    }
+}
+
+/// A stretch of silence within an audio file, in seconds.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SilentRegion {
+    /// Start of the silence
+    pub start: f64,
+    /// End of the silence
+    pub end: f64,
+}
+
+/// Converts a duration in milliseconds to a whole number of frames, at least one.
+pub fn ms_to_frames(ms: f64, sample_rate: u32) -> usize {
+    ((ms / 1000.0 * sample_rate as f64).round() as usize).max(1)
+}
+
+/// Measures the RMS level of consecutive windows of audio.
+///
+/// The RMS of a window is taken over all of its samples, so every channel
+/// counts equally. The last window may be shorter than the others.
+///
+/// # Arguments
+/// * `pcm` - Audio to analyze
+/// * `window` - Window length in frames
+///
+/// # Returns
+/// * `Vec<f64>` - Level of each window in dBFS; digital silence is negative infinity
+pub fn window_levels_db(pcm: &Pcm, window: usize) -> Vec<f64> {
+    let channels = pcm.channels.max(1) as usize;
+    pcm.samples[..pcm.frames() * channels]
+        .chunks(window.max(1) * channels)
+        .map(|block| {
+            let mean_square =
+                block.iter().map(|&s| (s as f64) * (s as f64)).sum::<f64>() / block.len() as f64;
+            10.0 * mean_square.log10()
+        })
+        .collect()
+}
+
+/// Finds silent frame ranges as `(start, end)` frame indices.
+fn silent_frame_ranges(pcm: &Pcm, options: &SilenceOptions) -> Vec<(usize, usize)> {
+    let frames = pcm.frames();
+    let window = ms_to_frames(options.window_ms, pcm.sample_rate);
+    let min_len = ms_to_frames(options.min_silence_ms, pcm.sample_rate);
+
+    let mut ranges = Vec::new();
+    let mut start = None;
+    for (w, level) in window_levels_db(pcm, window).into_iter().enumerate() {
+        if level < options.threshold_db {
+            start.get_or_insert(w * window);
+        } else if let Some(s) = start.take() {
+            ranges.push((s, w * window));
+        }
+    }
+    if let Some(s) = start {
+        ranges.push((s, frames));
+    }
+    ranges.retain(|(s, e)| e - s >= min_len);
+    ranges
+}
+
+/// Finds the silent regions of decoded audio.
+///
+/// # Arguments
+/// * `pcm` - Audio to analyze
+/// * `options` - Threshold, window length and minimum silence length
+///
+/// # Returns
+/// * `Vec<SilentRegion>` - Silent regions in order
+pub fn silent_regions(pcm: &Pcm, options: &SilenceOptions) -> Vec<SilentRegion> {
+    let rate = pcm.sample_rate as f64;
+    silent_frame_ranges(pcm, options)
+        .into_iter()
+        .map(|(s, e)| SilentRegion {
+            start: s as f64 / rate,
+            end: e as f64 / rate,
+        })
+        .collect()
+}
+
+/// Finds the silent regions of a WAV file.
+///
+/// Integer WAVs of any bit depth and float WAVs are supported.
+///
+/// # Arguments
+/// * `path` - Path to the WAV file to analyze
+/// * `options` - Threshold, window length and minimum silence length
+///
+/// # Returns
+/// * `Ok(Vec<SilentRegion>)` - Silent regions in order
+/// * `Err` - If the file cannot be read or is not a valid WAV file
+pub fn detect_silence(path: &str, options: &SilenceOptions) -> anyhow::Result<Vec<SilentRegion>> {
+    Ok(silent_regions(&read_pcm(path)?, options))
+}
 
 /// Detects the duration of leading silence in a WAV audio file.
 ///
@@ -10,35 +126,42 @@
 ///
 /// # Arguments
 /// * `path` - Path to the WAV file to analyze
-/// * `silence_threshold` - Amplitude threshold below which audio is considered silence
-/// * `min_silence_len` - Minimum number of samples required to count as silence
+/// * `options` - Threshold, window length and minimum silence length
 ///
 /// # Returns
 /// * `Ok(f64)` - Duration of leading silence in seconds
 /// * `Err` - If the file cannot be read or is not a valid WAV file
-pub fn detect_leading_silence(
-    path: &str,
-    silence_threshold: i16,
-    min_silence_len: usize,
-) -> anyhow::Result<f64> {
-    let mut reader = hound::WavReader::open(path)?;
-    let spec = reader.spec();
-    let sample_rate = spec.sample_rate as usize;
-    let mut silence_samples = 0;
-    for sample in reader.samples::<i16>() {
-        let s = sample?;
-        if s.abs() < silence_threshold {
-            silence_samples += 1;
-        } else if silence_samples >= min_silence_len {
-            break;
-        } else {
-            silence_samples = 0;
-        }
-    }
-    let silence_seconds = silence_samples as f64 / sample_rate as f64;
-    Ok(silence_seconds)
+pub fn detect_leading_silence(path: &str, options: &SilenceOptions) -> anyhow::Result<f64> {
+    let regions = detect_silence(path, options)?;
+    Ok(regions
+        .first()
+        .filter(|r| r.start == 0.0)
+        .map_or(0.0, |r| r.end))
 }
+
+/// Detects the duration of trailing silence in a WAV audio file.
+///
+/// Counterpart of [`detect_leading_silence`] for the end of a file, used to
+/// keep subtitles from running into the silence after the last word.
+///
+/// # Arguments
+/// * `path` - Path to the WAV file to analyze
+/// * `options` - Threshold, window length and minimum silence length
+///
+/// # Returns
+/// * `Ok(f64)` - Duration of trailing silence in seconds
+/// * `Err` - If the file cannot be read or is not a valid WAV file
+pub fn detect_trailing_silence(path: &str, options: &SilenceOptions) -> anyhow::Result<f64> {
+    let pcm = read_pcm(path)?;
+    let duration = pcm.frames() as f64 / pcm.sample_rate as f64;
+    Ok(silent_regions(&pcm, options)
+        .last()
+        .filter(|r| r.end >= duration)
+        .map_or(0.0, |r| r.end - r.start))
+}
+
 use hound::WavReader;
+use tracing::warn;
 
 /// Calculates the total duration of a WAV audio file in seconds.
 ///
@@ -56,3 +179,534 @@ pub fn wav_duration_seconds(path: &str) -> anyhow::Result<f64> {
     let duration = frames / spec.sample_rate as f64;
     Ok(duration)
 }
+
+/// Calculates the duration of an audio or video file in seconds.
+///
+/// WAV files are measured directly; other files are measured with ffprobe.
+///
+/// # Arguments
+/// * `path` - Path to the media file
+///
+/// # Returns
+/// * `Ok(f64)` - Duration in seconds
+/// * `Err` - If the file cannot be read or ffprobe fails
+pub fn media_duration_seconds(path: &str) -> anyhow::Result<f64> {
+    if WavReader::open(path).is_ok() {
+        return wav_duration_seconds(path);
+    }
+    let output = std::process::Command::new("ffprobe")
+        .args([
+            "-v",
+            "error",
+            "-show_entries",
+            "format=duration",
+            "-of",
+            "default=noprint_wrappers=1:nokey=1",
+            path,
+        ])
+        .output()?;
+    if !output.status.success() {
+        anyhow::bail!("ffprobe failed to read the duration of {}", path);
+    }
+    let duration = String::from_utf8_lossy(&output.stdout);
+    duration
+        .trim()
+        .parse()
+        .map_err(|_| anyhow::anyhow!("ffprobe reported no duration for {}", path))
+}
+
+/// Decoded audio as interleaved `f32` samples in the range `[-1, 1]`.
+#[derive(Debug, Clone)]
+pub struct Pcm {
+    /// Interleaved samples
+    pub samples: Vec<f32>,
+    /// Sample rate in Hz
+    pub sample_rate: u32,
+    /// Number of interleaved channels
+    pub channels: u16,
+}
+
+impl Pcm {
+    /// Number of frames (samples per channel).
+    pub fn frames(&self) -> usize {
+        self.samples.len() / self.channels.max(1) as usize
+    }
+}
+
+/// Reads a WAV file of any integer or float sample format into [`Pcm`].
+///
+/// # Arguments
+/// * `path` - Path to the WAV file to read
+///
+/// # Returns
+/// * `Ok(Pcm)` - The decoded audio
+/// * `Err` - If the file cannot be read or is not a valid WAV file
+pub fn read_pcm(path: &str) -> anyhow::Result<Pcm> {
+    let mut reader = WavReader::open(path)?;
+    let spec = reader.spec();
+    let samples = match spec.sample_format {
+        hound::SampleFormat::Int => {
+            let scale = (1i64 << (spec.bits_per_sample - 1)) as f32;
+            reader
+                .samples::<i32>()
+                .map(|s| s.map(|s| s as f32 / scale))
+                .collect::<Result<Vec<_>, _>>()?
+        }
+        hound::SampleFormat::Float => reader.samples::<f32>().collect::<Result<Vec<_>, _>>()?,
+    };
+    Ok(Pcm {
+        samples,
+        sample_rate: spec.sample_rate,
+        channels: spec.channels,
+    })
+}
+
+/// Writes [`Pcm`] audio as a 16-bit PCM WAV file, clipping out-of-range samples.
+///
+/// # Arguments
+/// * `path` - Output path for the WAV file
+/// * `pcm` - Audio to write
+///
+/// # Returns
+/// * `Ok(())` - If the file was written
+/// * `Err` - If the file cannot be created or written
+pub fn write_pcm(path: &str, pcm: &Pcm) -> anyhow::Result<()> {
+    let spec = hound::WavSpec {
+        channels: pcm.channels,
+        sample_rate: pcm.sample_rate,
+        bits_per_sample: 16,
+        sample_format: hound::SampleFormat::Int,
+    };
+    let mut writer = hound::WavWriter::create(path, spec)?;
+    for &s in &pcm.samples {
+        writer.write_sample((s.clamp(-1.0, 1.0) * i16::MAX as f32).round() as i16)?;
+    }
+    writer.finalize()?;
+    Ok(())
+}
+
+/// Converts audio to a different channel count.
+///
+/// Mono is duplicated to every output channel; anything else is first mixed
+/// down to mono.
+///
+/// # Arguments
+/// * `pcm` - Audio to convert
+/// * `channels` - Target number of channels
+///
+/// # Returns
+/// * `Pcm` - Audio with `channels` interleaved channels
+pub fn convert_channels(pcm: &Pcm, channels: u16) -> Pcm {
+    if pcm.channels == channels {
+        return pcm.clone();
+    }
+    let from = pcm.channels.max(1) as usize;
+    let mut samples = Vec::with_capacity(pcm.frames() * channels as usize);
+    for frame in pcm.samples.chunks_exact(from) {
+        let mono = frame.iter().sum::<f32>() / from as f32;
+        samples.extend(std::iter::repeat_n(mono, channels as usize));
+    }
+    Pcm {
+        samples,
+        sample_rate: pcm.sample_rate,
+        channels,
+    }
+}
+
+/// Resamples audio to a different sample rate using linear interpolation.
+///
+/// # Arguments
+/// * `pcm` - Audio to resample
+/// * `sample_rate` - Target sample rate in Hz
+///
+/// # Returns
+/// * `Pcm` - Audio at `sample_rate` with the same duration
+pub fn resample(pcm: &Pcm, sample_rate: u32) -> Pcm {
+    if pcm.sample_rate == sample_rate || pcm.frames() == 0 {
+        return Pcm {
+            sample_rate,
+            ..pcm.clone()
+        };
+    }
+    let channels = pcm.channels.max(1) as usize;
+    let in_frames = pcm.frames();
+    let ratio = pcm.sample_rate as f64 / sample_rate as f64;
+    let out_frames = (in_frames as f64 / ratio).round() as usize;
+    let mut samples = Vec::with_capacity(out_frames * channels);
+    for i in 0..out_frames {
+        let pos = i as f64 * ratio;
+        let i0 = (pos.floor() as usize).min(in_frames - 1);
+        let i1 = (i0 + 1).min(in_frames - 1);
+        let t = (pos - i0 as f64) as f32;
+        for c in 0..channels {
+            let a = pcm.samples[i0 * channels + c];
+            let b = pcm.samples[i1 * channels + c];
+            samples.push(a + (b - a) * t);
+        }
+    }
+    Pcm {
+        samples,
+        sample_rate,
+        channels: pcm.channels,
+    }
+}
+
+/// Concatenates WAV files into a single 16-bit WAV file.
+///
+/// The output uses the sample rate and channel count of the first input;
+/// inputs in other formats are converted and resampled to match, with a
+/// warning.
+///
+/// # Arguments
+/// * `inputs` - Paths of the WAV files to join, in order
+/// * `out_path` - Output path for the combined WAV file
+/// * `gap_seconds` - Silence inserted between consecutive inputs
+///
+/// # Returns
+/// * `Ok(())` - If the combined file was written
+/// * `Err` - If an input cannot be read or the output cannot be written
+pub fn concat_wavs(inputs: &[String], out_path: &str, gap_seconds: f64) -> anyhow::Result<()> {
+    let first = inputs
+        .first()
+        .ok_or_else(|| anyhow::anyhow!("No WAV files to concatenate"))?;
+    let first = read_pcm(first)?;
+    let (sample_rate, channels) = (first.sample_rate, first.channels);
+    let gap =
+        vec![0.0; (gap_seconds.max(0.0) * sample_rate as f64).round() as usize * channels as usize];
+
+    let mut combined = Pcm {
+        samples: Vec::new(),
+        sample_rate,
+        channels,
+    };
+    for (i, path) in inputs.iter().enumerate() {
+        let mut pcm = if i == 0 {
+            first.clone()
+        } else {
+            read_pcm(path)?
+        };
+        if pcm.sample_rate != sample_rate || pcm.channels != channels {
+            warn!(
+                "Converting {} from {} Hz/{} ch to {} Hz/{} ch",
+                path, pcm.sample_rate, pcm.channels, sample_rate, channels
+            );
+            pcm = resample(&convert_channels(&pcm, channels), sample_rate);
+        }
+        if i > 0 {
+            combined.samples.extend_from_slice(&gap);
+        }
+        combined.samples.extend_from_slice(&pcm.samples);
+    }
+    write_pcm(out_path, &combined)
+}
+
+/// Writes a WAV file of silence in the same format as an existing WAV file.
+///
+/// # Arguments
+/// * `like` - WAV file whose format (sample rate, channels, sample format) is copied
+/// * `out_path` - Output path for the silent WAV file
+/// * `seconds` - Length of the silence in seconds
+///
+/// # Returns
+/// * `Ok(())` - If the file was written
+/// * `Err` - If the reference file cannot be read or the output cannot be written
+pub fn write_silence_like(like: &str, out_path: &str, seconds: f64) -> anyhow::Result<()> {
+    let spec = WavReader::open(like)?.spec();
+    let frames = (seconds.max(0.0) * spec.sample_rate as f64).round() as usize;
+    let mut writer = hound::WavWriter::create(out_path, spec)?;
+    for _ in 0..frames * spec.channels as usize {
+        match spec.sample_format {
+            hound::SampleFormat::Int => writer.write_sample(0i32)?,
+            hound::SampleFormat::Float => writer.write_sample(0.0f32)?,
+        }
+    }
+    writer.finalize()?;
+    Ok(())
+}
+
+/// Settings for [`trim_silence`].
+#[derive(Debug, Clone, Copy)]
+pub struct TrimOptions {
+    /// How silence is detected
+    pub silence: SilenceOptions,
+    /// Seconds of silence kept before the first and after the last sound
+    pub pad_seconds: f64,
+    /// Internal pauses longer than this many seconds are shortened to it
+    pub max_pause: Option<f64>,
+}
+
+/// Silence removed from a file by [`trim_silence`].
+#[derive(Debug, Clone, Default)]
+pub struct TrimmedSilence {
+    /// Removed spans as (start in the original audio, length), both in seconds
+    cuts: Vec<(f64, f64)>,
+}
+
+impl TrimmedSilence {
+    /// Total seconds of silence removed.
+    pub fn removed(&self) -> f64 {
+        self.cuts.iter().map(|(_, len)| len).sum()
+    }
+
+    /// Maps a time in the original audio to the same point in the trimmed audio.
+    ///
+    /// Times inside a removed span collapse onto the cut point.
+    pub fn map_time(&self, t: f64) -> f64 {
+        let mut shift = 0.0;
+        for &(start, len) in &self.cuts {
+            if t >= start + len {
+                shift += len;
+            } else if t > start {
+                shift += t - start;
+            }
+        }
+        (t - shift).max(0.0)
+    }
+}
+
+/// Trims leading and trailing silence from a WAV file in place, and
+/// optionally shortens long pauses inside it.
+///
+/// Files that are silent throughout are left unchanged.
+///
+/// # Arguments
+/// * `path` - WAV file to trim; it is rewritten as 16-bit PCM if anything is removed
+/// * `options` - Threshold, retained padding and maximum internal pause
+///
+/// # Returns
+/// * `Ok(TrimmedSilence)` - The spans that were removed
+/// * `Err` - If the file cannot be read or written
+pub fn trim_silence(path: &str, options: &TrimOptions) -> anyhow::Result<TrimmedSilence> {
+    let pcm = read_pcm(path)?;
+    let frames = pcm.frames();
+    let mut silent = silent_frame_ranges(&pcm, &options.silence);
+    if silent.first() == Some(&(0, frames)) {
+        return Ok(TrimmedSilence::default());
+    }
+    let rate = pcm.sample_rate as f64;
+    let pad = (options.pad_seconds.max(0.0) * rate).round() as usize;
+
+    // Frame ranges to drop, in order.
+    let mut cuts = Vec::new();
+    let trailing = silent.pop_if(|&mut (_, end)| end == frames);
+    if let Some(&(0, end)) = silent.first() {
+        silent.remove(0);
+        if end > pad {
+            cuts.push((0, end - pad));
+        }
+    }
+    if let Some(max_pause) = options.max_pause {
+        let keep = (max_pause.max(0.0) * rate).round() as usize;
+        for (start, end) in silent {
+            // Keep half of the allowed pause on each side of the cut.
+            if end - start > keep {
+                cuts.push((start + keep / 2, end - (keep - keep / 2)));
+            }
+        }
+    }
+    if let Some((start, _)) = trailing
+        && start + pad < frames
+    {
+        cuts.push((start + pad, frames));
+    }
+    if cuts.is_empty() {
+        return Ok(TrimmedSilence::default());
+    }
+
+    let channels = pcm.channels.max(1) as usize;
+    let mut samples = Vec::with_capacity(pcm.samples.len());
+    let mut kept_from = 0;
+    for &(start, stop) in &cuts {
+        samples.extend_from_slice(&pcm.samples[kept_from * channels..start * channels]);
+        kept_from = stop;
+    }
+    samples.extend_from_slice(&pcm.samples[kept_from * channels..]);
+    write_pcm(path, &Pcm { samples, ..pcm })?;
+
+    Ok(TrimmedSilence {
+        cuts: cuts
+            .into_iter()
+            .map(|(start, stop)| (start as f64 / rate, (stop - start) as f64 / rate))
+            .collect(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    const SAMPLE_RATE: u32 = 16_000;
+
+    /// A temporary WAV path unique to this test.
+    fn temp_wav(name: &str) -> String {
+        std::env::temp_dir()
+            .join(format!("audio_{name}_{}.wav", std::process::id()))
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    /// Writes interleaved `values` (in -1..1) as a WAV in the given sample format.
+    fn write_wav(
+        path: &str,
+        values: &[f32],
+        channels: u16,
+        bits: u16,
+        format: hound::SampleFormat,
+    ) {
+        let spec = hound::WavSpec {
+            channels,
+            sample_rate: SAMPLE_RATE,
+            bits_per_sample: bits,
+            sample_format: format,
+        };
+        let mut writer = hound::WavWriter::create(path, spec).unwrap();
+        for &v in values {
+            match format {
+                hound::SampleFormat::Int => {
+                    let scale = (1i64 << (bits - 1)) as f32;
+                    writer.write_sample((v * scale) as i32).unwrap();
+                }
+                hound::SampleFormat::Float => writer.write_sample(v).unwrap(),
+            }
+        }
+        writer.finalize().unwrap();
+    }
+
+    /// `seconds` of a 440 Hz tone at half scale, repeated on every channel.
+    fn tone(seconds: f64, channels: u16) -> Vec<f32> {
+        let frames = (seconds * SAMPLE_RATE as f64) as usize;
+        (0..frames)
+            .flat_map(|n| {
+                let s = 0.5 * (n as f32 * 440.0 * std::f32::consts::TAU / SAMPLE_RATE as f32).sin();
+                std::iter::repeat_n(s, channels as usize)
+            })
+            .collect()
+    }
+
+    /// `seconds` of digital silence on every channel.
+    fn silence(seconds: f64, channels: u16) -> Vec<f32> {
+        vec![0.0; (seconds * SAMPLE_RATE as f64) as usize * channels as usize]
+    }
+
+    #[test]
+    fn every_sample_format_decodes_to_the_same_values() {
+        let values = [0.5, -0.25, 0.0, -1.0, 0.125, 0.75];
+        let formats = [
+            (16, hound::SampleFormat::Int),
+            (24, hound::SampleFormat::Int),
+            (32, hound::SampleFormat::Int),
+            (32, hound::SampleFormat::Float),
+        ];
+        for channels in [1, 2, 3] {
+            for (bits, format) in formats {
+                let path = temp_wav(&format!("format_{bits}_{format:?}_{channels}"));
+                write_wav(&path, &values, channels, bits, format);
+                let pcm = read_pcm(&path).unwrap();
+                fs::remove_file(&path).unwrap();
+                assert_eq!(pcm.sample_rate, SAMPLE_RATE);
+                assert_eq!(pcm.channels, channels);
+                assert_eq!(pcm.frames(), values.len() / channels as usize);
+                assert_eq!(pcm.samples, values, "{bits}-bit {format:?} x{channels}");
+            }
+        }
+    }
+
+    #[test]
+    fn gap_between_tones_is_found_in_mono_and_stereo() {
+        for channels in [1, 2] {
+            let samples = [
+                tone(0.3, channels),
+                silence(0.4, channels),
+                tone(0.3, channels),
+            ]
+            .concat();
+            let pcm = Pcm {
+                samples,
+                sample_rate: SAMPLE_RATE,
+                channels,
+            };
+            let regions = silent_regions(&pcm, &SilenceOptions::default());
+            assert_eq!(regions.len(), 1, "{channels} channels: {regions:?}");
+            assert!((regions[0].start - 0.3).abs() <= 0.01, "{regions:?}");
+            assert!((regions[0].end - 0.7).abs() <= 0.01, "{regions:?}");
+        }
+    }
+
+    #[test]
+    fn silence_is_detected_from_a_stereo_wav_file() {
+        let path = temp_wav("stereo_gap");
+        let samples = [tone(0.2, 2), silence(0.3, 2), tone(0.2, 2)].concat();
+        write_wav(&path, &samples, 2, 24, hound::SampleFormat::Int);
+        let regions = detect_silence(&path, &SilenceOptions::default()).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(regions.len(), 1, "{regions:?}");
+        assert!((regions[0].start - 0.2).abs() <= 0.01, "{regions:?}");
+        assert!((regions[0].end - 0.5).abs() <= 0.01, "{regions:?}");
+    }
+
+    #[test]
+    fn trimming_removes_edges_and_shortens_long_pauses() {
+        let path = temp_wav("trim");
+        let samples = [
+            silence(0.5, 1),
+            tone(0.3, 1),
+            silence(1.0, 1),
+            tone(0.3, 1),
+            silence(0.4, 1),
+        ]
+        .concat();
+        write_wav(&path, &samples, 1, 16, hound::SampleFormat::Int);
+        let options = TrimOptions {
+            silence: SilenceOptions::default(),
+            pad_seconds: 0.1,
+            max_pause: Some(0.3),
+        };
+        let trimmed = trim_silence(&path, &options).unwrap();
+        let duration = wav_duration_seconds(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        // Leading silence keeps 0.1s, the 1s pause keeps 0.3s and trailing keeps 0.1s.
+        let expected = [(0.0, 0.4), (0.95, 0.7), (2.2, 0.3)];
+        assert_eq!(trimmed.cuts.len(), expected.len(), "{:?}", trimmed.cuts);
+        for (&(start, len), (want_start, want_len)) in trimmed.cuts.iter().zip(expected) {
+            assert!((start - want_start).abs() <= 0.011, "{:?}", trimmed.cuts);
+            assert!((len - want_len).abs() <= 0.011, "{:?}", trimmed.cuts);
+        }
+        assert!((trimmed.removed() - 1.4).abs() <= 0.03);
+        assert!((duration - (2.5 - trimmed.removed())).abs() < 1e-3);
+    }
+
+    #[test]
+    fn trimming_leaves_silent_files_alone() {
+        let path = temp_wav("trim_silent");
+        write_wav(&path, &silence(0.5, 2), 2, 16, hound::SampleFormat::Int);
+        let options = TrimOptions {
+            silence: SilenceOptions::default(),
+            pad_seconds: 0.1,
+            max_pause: Some(0.3),
+        };
+        let trimmed = trim_silence(&path, &options).unwrap();
+        let duration = wav_duration_seconds(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(trimmed.removed(), 0.0);
+        assert!((duration - 0.5).abs() < 1e-3);
+    }
+
+    #[test]
+    fn times_shift_after_cuts_and_clamp_inside_them() {
+        let trimmed = TrimmedSilence {
+            cuts: vec![(0.0, 0.4), (1.0, 0.5)],
+        };
+        let map = |t| trimmed.map_time(t);
+        // Inside the leading cut.
+        assert_eq!(map(0.2), 0.0);
+        // Between the cuts.
+        assert!((map(0.7) - 0.3).abs() < 1e-9);
+        // Inside the second cut collapses onto its start.
+        assert!((map(1.2) - 0.6).abs() < 1e-9);
+        assert!((map(1.5) - 0.6).abs() < 1e-9);
+        // After both cuts.
+        assert!((map(2.0) - 1.1).abs() < 1e-9);
+    }
+}
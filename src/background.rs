@@ -0,0 +1,363 @@
+//! Choice of the background clip and segment shown behind the narration.
+//!
+//! `--background` is either one clip or a library directory of clips. A
+//! library may contain a `backgrounds.json` manifest mapping file names to
+//! tags (`{"parkour.mp4": ["minecraft", "calm"]}`); clips are filtered by tag
+//! and picked either least recently used or at random. Usage is kept in a
+//! small JSON file so consecutive videos avoid repeating a clip.
+//!
+//! Each video starts the background at a random offset so consecutive videos
+//! do not all show the same opening minute of footage. Clip choice and offset
+//! come from a seeded generator, so a render can be reproduced from the seed
+//! in its metadata. Backgrounds shorter than the narration are looped instead.
+
+use crate::audio;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::{debug, info};
+
+/// File extensions considered video clips in a background library.
+const VIDEO_EXTENSIONS: &[&str] = &["mp4", "mkv", "mov", "webm", "m4v", "avi"];
+/// Name of the tag manifest inside a background library.
+const MANIFEST_FILE: &str = "backgrounds.json";
+
+/// How a clip is chosen from a background library.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum BackgroundPick {
+    /// The clip that was used longest ago, or never
+    Lru,
+    /// Any matching clip
+    Random,
+}
+
+/// A background clip and its tags.
+#[derive(Debug, Clone)]
+pub struct BackgroundClip {
+    /// Path of the video file
+    pub path: String,
+    /// Tags from the library manifest
+    pub tags: Vec<String>,
+}
+
+/// How often and when a clip was used.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct ClipUsage {
+    /// Unix time of the last render that used the clip
+    last_used: u64,
+    /// Number of renders that used the clip
+    uses: u64,
+}
+
+/// Lists the clips of a background library with their manifest tags.
+///
+/// # Arguments
+/// * `dir` - Library directory
+///
+/// # Returns
+/// * `Ok(Vec<BackgroundClip>)` - Clips sorted by path
+/// * `Err` - If the directory or its manifest cannot be read
+fn list_clips(dir: &Path) -> anyhow::Result<Vec<BackgroundClip>> {
+    let manifest_path = dir.join(MANIFEST_FILE);
+    let manifest: HashMap<String, Vec<String>> = if manifest_path.exists() {
+        serde_json::from_str(&fs::read_to_string(&manifest_path)?).map_err(|e| {
+            anyhow::anyhow!(
+                "Invalid background manifest {}: {e}",
+                manifest_path.display()
+            )
+        })?
+    } else {
+        HashMap::new()
+    };
+    let mut clips: Vec<BackgroundClip> = fs::read_dir(dir)?
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|p| {
+            p.extension()
+                .and_then(|e| e.to_str())
+                .is_some_and(|e| VIDEO_EXTENSIONS.contains(&e.to_lowercase().as_str()))
+        })
+        .map(|p| {
+            let name = p
+                .file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            BackgroundClip {
+                path: p.to_string_lossy().into_owned(),
+                tags: manifest.get(&name).cloned().unwrap_or_default(),
+            }
+        })
+        .collect();
+    clips.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(clips)
+}
+
+fn load_usage(path: &str) -> anyhow::Result<BTreeMap<String, ClipUsage>> {
+    if !Path::new(path).exists() {
+        return Ok(BTreeMap::new());
+    }
+    Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+}
+
+/// Picks the background clip for a video.
+///
+/// # Arguments
+/// * `path` - A clip, or a library directory to pick from
+/// * `tags` - Tags a library clip must all have (case-insensitive)
+/// * `pick` - How to choose among matching clips
+/// * `usage_path` - JSON file recording when each clip was last used
+/// * `seed` - Seed for random choices
+///
+/// # Returns
+/// * `Ok(BackgroundClip)` - The chosen clip
+/// * `Err` - If the path does not exist or no clip matches the tags
+pub fn pick_background(
+    path: &str,
+    tags: &[String],
+    pick: BackgroundPick,
+    usage_path: &str,
+    seed: u64,
+) -> anyhow::Result<BackgroundClip> {
+    let dir = Path::new(path);
+    if !dir.is_dir() {
+        anyhow::ensure!(dir.exists(), "Background video not found: {path}");
+        return Ok(BackgroundClip {
+            path: path.to_string(),
+            tags: Vec::new(),
+        });
+    }
+    let clips = list_clips(dir)?;
+    anyhow::ensure!(!clips.is_empty(), "No video clips found in {path}");
+    let matching: Vec<&BackgroundClip> = clips
+        .iter()
+        .filter(|clip| {
+            tags.iter()
+                .all(|tag| clip.tags.iter().any(|t| t.eq_ignore_ascii_case(tag)))
+        })
+        .collect();
+    if matching.is_empty() {
+        let mut known: Vec<&str> = clips
+            .iter()
+            .flat_map(|c| c.tags.iter().map(String::as_str))
+            .collect();
+        known.sort_unstable_by_key(|t| t.to_lowercase());
+        known.dedup_by(|a, b| a.eq_ignore_ascii_case(b));
+        anyhow::bail!(
+            "No background clip in {path} has the tags {} (known tags: {})",
+            tags.join(", "),
+            known.join(", ")
+        );
+    }
+
+    let mut rng = fastrand::Rng::with_seed(seed);
+    let candidates = match pick {
+        BackgroundPick::Random => matching,
+        BackgroundPick::Lru => {
+            let usage = load_usage(usage_path)?;
+            let last_used =
+                |clip: &BackgroundClip| usage.get(&clip.path).map_or(0, |u| u.last_used);
+            let oldest = matching.iter().map(|c| last_used(c)).min().unwrap_or(0);
+            matching
+                .into_iter()
+                .filter(|c| last_used(c) == oldest)
+                .collect()
+        }
+    };
+    debug!(
+        "Choosing a background from {} candidate clips",
+        candidates.len()
+    );
+    let clip = candidates[rng.usize(..candidates.len())].clone();
+    info!(
+        "Picked background clip {} (tags: {}, seed {})",
+        clip.path,
+        clip.tags.join(", "),
+        seed
+    );
+    Ok(clip)
+}
+
+/// Records that a clip was used, for least-recently-used picking.
+///
+/// # Arguments
+/// * `usage_path` - JSON file recording clip usage
+/// * `clip` - Path of the clip that was used
+///
+/// # Returns
+/// * `Ok(())` - If the usage file was written
+/// * `Err` - If the usage file cannot be read or written
+pub fn record_usage(usage_path: &str, clip: &str) -> anyhow::Result<()> {
+    let mut usage = load_usage(usage_path)?;
+    let entry = usage.entry(clip.to_string()).or_default();
+    entry.last_used = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+    entry.uses += 1;
+    fs::write(usage_path, serde_json::to_string_pretty(&usage)?)?;
+    Ok(())
+}
+
+/// The part of a background video used for one render.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackgroundSegment {
+    /// Background video file
+    pub path: String,
+    /// Tags of the clip in its library manifest
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Where in the background the video starts, in seconds
+    pub offset: f64,
+    /// Whether the background repeats because it is shorter than the narration
+    pub looped: bool,
+    /// Seed the clip and offset were chosen with
+    pub seed: u64,
+}
+
+/// Chooses where to start the background so that it covers the narration.
+///
+/// # Arguments
+/// * `clip` - Background clip
+/// * `narration_seconds` - Length of the narration the background must cover
+/// * `seed` - Seed for the random start offset
+///
+/// # Returns
+/// * `Ok(BackgroundSegment)` - The chosen segment
+/// * `Err` - If the background's duration cannot be read
+pub fn choose_segment(
+    clip: &BackgroundClip,
+    narration_seconds: f64,
+    seed: u64,
+) -> anyhow::Result<BackgroundSegment> {
+    let path = clip.path.as_str();
+    let duration = audio::media_duration_seconds(path)?;
+    let slack = duration - narration_seconds;
+    let segment = if slack >= 0.0 {
+        let mut rng = fastrand::Rng::with_seed(seed);
+        BackgroundSegment {
+            path: path.to_string(),
+            tags: clip.tags.clone(),
+            offset: rng.f64() * slack,
+            looped: false,
+            seed,
+        }
+    } else {
+        BackgroundSegment {
+            path: path.to_string(),
+            tags: clip.tags.clone(),
+            offset: 0.0,
+            looped: true,
+            seed,
+        }
+    };
+    if segment.looped {
+        info!(
+            "Background {} ({:.1}s) is shorter than the narration ({:.1}s); looping it (seed {})",
+            path, duration, narration_seconds, seed
+        );
+    } else {
+        info!(
+            "Background {} starts at {:.3}s of {:.1}s (seed {})",
+            path, segment.offset, duration, seed
+        );
+    }
+    Ok(segment)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Creates a library of empty clips with a tag manifest and a usage history.
+    fn library(name: &str) -> (std::path::PathBuf, String) {
+        let dir = std::env::temp_dir().join(format!("backgrounds_{name}_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        for file in ["a.mp4", "b.mp4", "c.MKV", "notes.txt"] {
+            fs::write(dir.join(file), b"").unwrap();
+        }
+        fs::write(
+            dir.join(MANIFEST_FILE),
+            r#"{"a.mp4": ["minecraft"], "b.mp4": ["Minecraft", "calm"], "c.MKV": ["subway"]}"#,
+        )
+        .unwrap();
+        let path = |file: &str| dir.join(file).to_string_lossy().into_owned();
+        let usage = BTreeMap::from([
+            (
+                path("a.mp4"),
+                ClipUsage {
+                    last_used: 200,
+                    uses: 3,
+                },
+            ),
+            (
+                path("b.mp4"),
+                ClipUsage {
+                    last_used: 100,
+                    uses: 1,
+                },
+            ),
+        ]);
+        let usage_path = dir.join("usage.json").to_string_lossy().into_owned();
+        fs::write(&usage_path, serde_json::to_string(&usage).unwrap()).unwrap();
+        (dir, usage_path)
+    }
+
+    fn file_name(clip: &BackgroundClip) -> String {
+        Path::new(&clip.path)
+            .file_name()
+            .unwrap()
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    #[test]
+    fn least_recently_used_matching_clip_is_picked() {
+        let (dir, usage) = library("lru");
+        let path = dir.to_str().unwrap();
+        let minecraft = ["MINECRAFT".to_string()];
+        for seed in 0..5 {
+            let clip =
+                pick_background(path, &minecraft, BackgroundPick::Lru, &usage, seed).unwrap();
+            assert_eq!(file_name(&clip), "b.mp4");
+            assert_eq!(clip.tags, ["Minecraft", "calm"]);
+        }
+        // The never-used clip is excluded by its tag, not picked as the oldest.
+        record_usage(&usage, &dir.join("b.mp4").to_string_lossy()).unwrap();
+        let clip = pick_background(path, &minecraft, BackgroundPick::Lru, &usage, 0).unwrap();
+        assert_eq!(file_name(&clip), "a.mp4");
+        // Without tags the never-used clip wins.
+        let clip = pick_background(path, &[], BackgroundPick::Lru, &usage, 0).unwrap();
+        assert_eq!(file_name(&clip), "c.MKV");
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn clips_without_the_tags_are_excluded() {
+        let (dir, usage) = library("tags");
+        let path = dir.to_str().unwrap();
+        for seed in 0..20 {
+            let clip = pick_background(
+                path,
+                &["subway".to_string()],
+                BackgroundPick::Random,
+                &usage,
+                seed,
+            )
+            .unwrap();
+            assert_eq!(file_name(&clip), "c.MKV");
+        }
+        let tags = ["minecraft".to_string(), "calm".to_string()];
+        let clip = pick_background(path, &tags, BackgroundPick::Random, &usage, 7).unwrap();
+        assert_eq!(file_name(&clip), "b.mp4");
+        let error = pick_background(path, &["beach".to_string()], BackgroundPick::Lru, &usage, 0)
+            .unwrap_err()
+            .to_string();
+        // Tags differing only in case are listed once.
+        assert!(
+            error
+                .to_lowercase()
+                .contains("known tags: calm, minecraft, subway"),
+            "{error}"
+        );
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
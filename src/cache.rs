@@ -0,0 +1,418 @@
+//! Content-addressed on-disk cache of synthesized TTS chunks.
+//!
+//! Each chunk is stored under the SHA-256 of the engine's cache key and the
+//! normalized chunk text, so re-rendering a story with a different background,
+//! subtitle style or resolution reuses the existing audio. Eviction removes
+//! least recently used entries until the cache fits its size cap.
+
+use crate::tts::{TtsEngine, TtsError, WordTiming, validate_wav};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, SystemTime};
+use tracing::{debug, info, warn};
+
+/// Age after which a `.tmp` file is assumed to be left over from a crashed
+/// run rather than being written by a concurrent one.
+const STALE_TMP_AGE: Duration = Duration::from_secs(60 * 60);
+
+/// Counter making temporary file names unique within this process.
+static TMP_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// A directory of cached WAV files keyed by content hash.
+pub struct TtsCache {
+    dir: PathBuf,
+}
+
+impl TtsCache {
+    /// Opens the cache directory, creating it if needed.
+    ///
+    /// # Arguments
+    /// * `dir` - Directory holding the cached files
+    ///
+    /// # Returns
+    /// * `Ok(TtsCache)` - The opened cache
+    /// * `Err` - If the directory cannot be created
+    pub fn open(dir: &str) -> anyhow::Result<Self> {
+        fs::create_dir_all(dir)?;
+        Ok(Self {
+            dir: PathBuf::from(dir),
+        })
+    }
+
+    /// Computes the cache key for a chunk.
+    ///
+    /// Whitespace in the text is normalized so that re-wrapped but otherwise
+    /// identical chunks share an entry.
+    ///
+    /// # Arguments
+    /// * `engine_key` - The engine's [`TtsEngine::cache_key`]
+    /// * `text` - Chunk text
+    ///
+    /// # Returns
+    /// * `String` - Hex-encoded SHA-256 digest
+    pub fn key(engine_key: &str, text: &str) -> String {
+        let normalized = text.split_whitespace().collect::<Vec<_>>().join(" ");
+        let mut hasher = Sha256::new();
+        hasher.update(engine_key.as_bytes());
+        hasher.update([0u8]);
+        hasher.update(normalized.as_bytes());
+        hasher
+            .finalize()
+            .iter()
+            .map(|b| format!("{b:02x}"))
+            .collect()
+    }
+
+    fn wav_path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{key}.wav"))
+    }
+
+    fn timings_path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{key}.timings.json"))
+    }
+
+    /// Writes `path` by way of a temporary file, so readers never see a partial file.
+    ///
+    /// The temporary name is unique to this process and call, so workers
+    /// storing the same entry at once do not write to the same file.
+    fn write_atomically(
+        path: &Path,
+        write: impl FnOnce(&Path) -> std::io::Result<()>,
+    ) -> anyhow::Result<()> {
+        let n = TMP_COUNTER.fetch_add(1, Ordering::Relaxed);
+        let mut tmp = path.as_os_str().to_owned();
+        tmp.push(format!(".{}.{n}.tmp", std::process::id()));
+        let tmp = PathBuf::from(tmp);
+        let written = write(&tmp).and_then(|_| fs::rename(&tmp, path));
+        if written.is_err() {
+            let _ = fs::remove_file(&tmp);
+        }
+        Ok(written?)
+    }
+
+    /// Copies a cached chunk to `out_path` if present.
+    ///
+    /// # Arguments
+    /// * `key` - Cache key from [`TtsCache::key`]
+    /// * `out_path` - Where the cached WAV should be copied
+    ///
+    /// # Returns
+    /// * `Ok(Some(timings))` - Cache hit, with word timings if they were stored
+    /// * `Ok(None)` - Cache miss
+    /// * `Err` - If a cached entry exists but cannot be copied
+    pub fn fetch(
+        &self,
+        key: &str,
+        out_path: &str,
+    ) -> anyhow::Result<Option<Option<Vec<WordTiming>>>> {
+        let wav = self.wav_path(key);
+        if !wav.exists() {
+            return Ok(None);
+        }
+        fs::copy(&wav, out_path)?;
+        // Refresh the modification time so eviction treats this entry as recently used.
+        fs::File::options()
+            .write(true)
+            .open(&wav)?
+            .set_modified(SystemTime::now())?;
+        let timings = match fs::read_to_string(self.timings_path(key)) {
+            Ok(data) => Some(serde_json::from_str(&data)?),
+            Err(_) => None,
+        };
+        Ok(Some(timings))
+    }
+
+    /// Stores a freshly synthesized chunk.
+    ///
+    /// The WAV is written before the timings, so a chunk's timings never
+    /// appear without its audio.
+    ///
+    /// # Arguments
+    /// * `key` - Cache key from [`TtsCache::key`]
+    /// * `wav_path` - Path of the synthesized WAV file
+    /// * `timings` - Word timings reported by the engine, if any
+    ///
+    /// # Returns
+    /// * `Ok(())` - If the entry was written
+    /// * `Err` - If the files cannot be copied or written
+    pub fn store(
+        &self,
+        key: &str,
+        wav_path: &str,
+        timings: Option<&Vec<WordTiming>>,
+    ) -> anyhow::Result<()> {
+        Self::write_atomically(&self.wav_path(key), |tmp| {
+            fs::copy(wav_path, tmp).map(|_| ())
+        })?;
+        if let Some(timings) = timings {
+            let data = serde_json::to_string(timings)?;
+            Self::write_atomically(&self.timings_path(key), |tmp| fs::write(tmp, data))?;
+        }
+        Ok(())
+    }
+
+    /// Removes a cached chunk, e.g. because its WAV turned out to be corrupt.
+    ///
+    /// # Arguments
+    /// * `key` - Cache key from [`TtsCache::key`]
+    pub fn remove(&self, key: &str) -> anyhow::Result<()> {
+        remove_entry(&self.wav_path(key))
+    }
+
+    /// Removes least recently used entries until the cache is at most `max_bytes`.
+    ///
+    /// Temporary files left behind by interrupted stores are swept as well
+    /// once they are older than an hour, and count towards the removed files.
+    ///
+    /// # Arguments
+    /// * `max_bytes` - Size cap for the cache directory; `0` empties it
+    ///
+    /// # Returns
+    /// * `Ok((files, bytes))` - Number of files and bytes removed
+    /// * `Err` - If the cache directory cannot be read
+    pub fn evict(&self, max_bytes: u64) -> anyhow::Result<(usize, u64)> {
+        let mut entries = Vec::new();
+        let mut total = 0;
+        let (mut removed, mut freed) = (0, 0);
+        for entry in fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            if path.extension().and_then(|e| e.to_str()) == Some("tmp") {
+                let meta = fs::metadata(&path)?;
+                let age = meta.modified()?.elapsed().unwrap_or_default();
+                if age >= STALE_TMP_AGE {
+                    fs::remove_file(&path)?;
+                    removed += 1;
+                    freed += meta.len();
+                }
+                continue;
+            }
+            if path.extension().and_then(|e| e.to_str()) != Some("wav") {
+                continue;
+            }
+            let meta = fs::metadata(&path)?;
+            let timings = path.with_extension("timings.json");
+            let size = meta.len() + fs::metadata(&timings).map(|m| m.len()).unwrap_or(0);
+            total += size;
+            entries.push((meta.modified()?, size, path));
+        }
+        entries.sort();
+
+        for (_, size, path) in entries {
+            if total <= max_bytes {
+                break;
+            }
+            remove_entry(&path)?;
+            total -= size;
+            freed += size;
+            removed += 1;
+        }
+        if removed > 0 {
+            info!(
+                "Evicted {} cached TTS files ({} bytes) from {}",
+                removed,
+                freed,
+                self.dir.display()
+            );
+        }
+        Ok((removed, freed))
+    }
+}
+
+/// Deletes a cached WAV and its timings file.
+fn remove_entry(wav: &Path) -> anyhow::Result<()> {
+    fs::remove_file(wav)?;
+    let timings = wav.with_extension("timings.json");
+    if timings.exists() {
+        fs::remove_file(timings)?;
+    }
+    Ok(())
+}
+
+/// Engine wrapper that serves chunks from a [`TtsCache`] when possible.
+///
+/// Cache failures are logged and otherwise ignored, so a broken cache never
+/// stops synthesis.
+pub struct CachedEngine {
+    inner: Box<dyn TtsEngine>,
+    cache: TtsCache,
+}
+
+impl CachedEngine {
+    /// Wraps `inner` so its output is cached in `cache`.
+    pub fn new(inner: Box<dyn TtsEngine>, cache: TtsCache) -> Self {
+        Self { inner, cache }
+    }
+}
+
+impl TtsEngine for CachedEngine {
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    fn cache_key(&self) -> String {
+        self.inner.cache_key()
+    }
+
+    fn supports_phonemes(&self) -> bool {
+        self.inner.supports_phonemes()
+    }
+
+    fn synthesize(&self, text: &str, out_path: &str) -> Result<Option<Vec<WordTiming>>, TtsError> {
+        let key = TtsCache::key(&self.inner.cache_key(), text);
+        match self.cache.fetch(&key, out_path) {
+            Ok(Some(timings)) => match validate_wav(out_path) {
+                Ok(()) => {
+                    debug!("TTS cache hit for {}", out_path);
+                    return Ok(timings);
+                }
+                Err(e) => {
+                    warn!("Discarding corrupt TTS cache entry {}: {}", key, e);
+                    if let Err(e) = self.cache.remove(&key) {
+                        warn!("Failed to remove TTS cache entry {}: {}", key, e);
+                    }
+                }
+            },
+            Ok(None) => {}
+            Err(e) => warn!("Failed to read TTS cache entry {}: {}", key, e),
+        }
+        let timings = self.inner.synthesize(text, out_path)?;
+        validate_wav(out_path)?;
+        if let Err(e) = self.cache.store(&key, out_path, timings.as_ref()) {
+            warn!("Failed to store TTS cache entry {}: {}", key, e);
+        }
+        Ok(timings)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// An engine that writes a short tone and counts its calls.
+    struct CountingEngine {
+        calls: Arc<AtomicUsize>,
+    }
+
+    impl TtsEngine for CountingEngine {
+        fn name(&self) -> &str {
+            "counting"
+        }
+
+        fn cache_key(&self) -> String {
+            "counting".to_string()
+        }
+
+        fn synthesize(
+            &self,
+            _text: &str,
+            out_path: &str,
+        ) -> Result<Option<Vec<WordTiming>>, TtsError> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            let spec = hound::WavSpec {
+                channels: 1,
+                sample_rate: 16_000,
+                bits_per_sample: 16,
+                sample_format: hound::SampleFormat::Int,
+            };
+            let mut writer = hound::WavWriter::create(out_path, spec).unwrap();
+            for i in 0..1600 {
+                writer.write_sample(((i % 40) * 500) as i16).unwrap();
+            }
+            writer.finalize().unwrap();
+            Ok(None)
+        }
+    }
+
+    /// Creates an empty cache directory unique to this test.
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("tts_cache_{name}_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn corrupt_entry_is_resynthesized() {
+        let dir = test_dir("corrupt");
+        let cache = TtsCache::open(dir.to_str().unwrap()).unwrap();
+        let key = TtsCache::key("counting", "hello");
+        fs::write(cache.wav_path(&key), b"RIFF").unwrap();
+
+        let calls = Arc::new(AtomicUsize::new(0));
+        let engine = CachedEngine::new(
+            Box::new(CountingEngine {
+                calls: calls.clone(),
+            }),
+            cache,
+        );
+        let out = dir.join("out.wav");
+        let out = out.to_str().unwrap();
+        engine.synthesize("hello", out).unwrap();
+        validate_wav(out).unwrap();
+        validate_wav(engine.cache.wav_path(&key).to_str().unwrap()).unwrap();
+
+        // The repaired entry is now served from the cache.
+        engine.synthesize("hello", out).unwrap();
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn eviction_sweeps_stale_temporary_files() {
+        let dir = test_dir("tmp");
+        let cache = TtsCache::open(dir.to_str().unwrap()).unwrap();
+        let stale = dir.join("a.wav.41.0.tmp");
+        let fresh = dir.join("b.timings.json.42.3.tmp");
+        fs::write(&stale, [0u8; 10]).unwrap();
+        fs::write(&fresh, [0u8; 10]).unwrap();
+        fs::File::options()
+            .write(true)
+            .open(&stale)
+            .unwrap()
+            .set_modified(SystemTime::now() - 2 * STALE_TMP_AGE)
+            .unwrap();
+
+        assert_eq!(cache.evict(u64::MAX).unwrap(), (1, 10));
+        assert!(!stale.exists());
+        assert!(fresh.exists());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn stored_entries_are_fetched_without_leftover_files() {
+        let dir = test_dir("store");
+        let cache = TtsCache::open(dir.to_str().unwrap()).unwrap();
+        let wav = dir.join("chunk.wav");
+        let wav = wav.to_str().unwrap();
+        CountingEngine {
+            calls: Arc::new(AtomicUsize::new(0)),
+        }
+        .synthesize("hello", wav)
+        .unwrap();
+        let timings = vec![WordTiming {
+            word: "hello".to_string(),
+            start: 0.0,
+            end: 0.1,
+        }];
+        let key = TtsCache::key("counting", "hello");
+        cache.store(&key, wav, Some(&timings)).unwrap();
+        cache.store(&key, wav, Some(&timings)).unwrap();
+
+        let out = dir.join("out.wav");
+        let fetched = cache.fetch(&key, out.to_str().unwrap()).unwrap();
+        let fetched = fetched.unwrap().unwrap();
+        assert_eq!(fetched.len(), 1);
+        assert_eq!(fetched[0].word, "hello");
+        let leftovers: Vec<_> = fs::read_dir(&dir)
+            .unwrap()
+            .map(|e| e.unwrap().path())
+            .filter(|p| p.extension().and_then(|e| e.to_str()) == Some("tmp"))
+            .collect();
+        assert!(leftovers.is_empty(), "{leftovers:?}");
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
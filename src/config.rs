@@ -0,0 +1,136 @@
+//! Optional JSON configuration file support.
+//!
+//! Settings in the configuration file act as defaults; any value passed on the
+//! command line takes precedence over the file.
+
+use crate::emphasis::KeywordStyle;
+use crate::output::OutputConfig;
+use crate::style::SubtitleStyle;
+use crate::title_card::TitleCardConfig;
+use crate::tts::TtsEngineKind;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use tracing::info;
+
+/// Top-level configuration file structure.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// Text-to-speech engine settings
+    pub tts: TtsConfig,
+    /// Subtitle style settings
+    pub subtitles: SubtitleConfig,
+    /// Output video profile settings
+    pub output: OutputConfig,
+    /// Title card template settings
+    pub title_card: TitleCardConfig,
+}
+
+/// Text-to-speech section of the configuration file.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct TtsConfig {
+    /// Which TTS backend to use
+    pub engine: Option<TtsEngineKind>,
+    /// Path to the Piper TTS model file (.onnx format)
+    pub piper_model: Option<String>,
+    /// Whether Piper gets inline `[[phonemes]]` from the lexicon [default: detected from the model]
+    pub piper_phonemes: Option<bool>,
+    /// Maximum number of chunks synthesized concurrently
+    pub jobs: Option<usize>,
+    /// eSpeak NG voice name (e.g. `en-us`)
+    pub espeak_voice: Option<String>,
+    /// Command template for the generic command engine
+    pub command: Option<String>,
+    /// Name of the voice profile used for narration
+    pub profile: Option<String>,
+    /// Named voice profiles
+    pub profiles: HashMap<String, VoiceProfile>,
+    /// Voice profile for the post title
+    pub title_profile: Option<String>,
+    /// Voice profile for quoted dialogue; enables multi-voice narration
+    pub dialogue_profile: Option<String>,
+    /// Voice profiles for specific speakers of dialogue (e.g. `"sister"`, `"mil"`)
+    pub character_profiles: HashMap<String, String>,
+}
+
+/// Subtitle section of the configuration file.
+///
+/// Style values set directly in this section override the selected preset.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct SubtitleConfig {
+    /// Name of the subtitle preset to start from
+    pub preset: Option<String>,
+    /// Named subtitle presets, layered over the built-in preset of the same name
+    pub presets: HashMap<String, SubtitleStyle>,
+    /// Keywords drawn with their own colour, size or emoji in burned-in captions
+    pub emphasis: HashMap<String, KeywordStyle>,
+    /// Style overrides applied on top of the preset
+    #[serde(flatten)]
+    pub style: SubtitleStyle,
+}
+
+/// A named set of voice parameters for the Piper engine.
+///
+/// Unset values fall back to the defaults in the model's `.onnx.json` file.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct VoiceProfile {
+    /// Piper model used by this profile instead of the global one
+    pub piper_model: Option<String>,
+    /// Speaker id or name for multi-speaker models
+    pub speaker: Option<SpeakerRef>,
+    /// Phoneme length multiplier; larger values speak more slowly
+    pub length_scale: Option<f32>,
+    /// Generator noise
+    pub noise_scale: Option<f32>,
+    /// Phoneme width noise
+    pub noise_w: Option<f32>,
+    /// Seconds of silence inserted after each sentence
+    pub sentence_silence: Option<f32>,
+    /// Subtitle colour for words spoken by this voice, as `#RRGGBB`
+    pub subtitle_colour: Option<String>,
+}
+
+/// Reference to a speaker of a multi-speaker model, by numeric id or by name.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(untagged)]
+pub enum SpeakerRef {
+    /// Numeric speaker id
+    Id(u32),
+    /// Speaker name from the model's `speaker_id_map`
+    Name(String),
+}
+
+impl std::str::FromStr for SpeakerRef {
+    type Err = std::convert::Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s.parse::<u32>() {
+            Ok(id) => SpeakerRef::Id(id),
+            Err(_) => SpeakerRef::Name(s.to_string()),
+        })
+    }
+}
+
+/// Loads the configuration file, falling back to defaults if it does not exist.
+///
+/// # Arguments
+/// * `path` - Path to the JSON configuration file
+///
+/// # Returns
+/// * `Ok(Config)` - Parsed configuration, or the default configuration if the file is missing
+/// * `Err` - If the file exists but cannot be read or parsed
+pub fn load_config(path: &str) -> anyhow::Result<Config> {
+    if !Path::new(path).exists() {
+        return Ok(Config::default());
+    }
+    let data = fs::read_to_string(path)?;
+    let config: Config = serde_json::from_str(&data)
+        .map_err(|e| anyhow::anyhow!("Invalid config file {path}: {e}"))?;
+    info!("Loaded configuration from {}", path);
+    Ok(config)
+}
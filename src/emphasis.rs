@@ -0,0 +1,187 @@
+//! Keyword emphasis for burned-in captions.
+//!
+//! Emotional keywords ("cheated", "divorce", "NTA") can be drawn in their own
+//! colour, enlarged, or followed by an emoji. Keywords are matched as whole
+//! words, case-insensitively and ignoring surrounding punctuation. Emphasis
+//! only changes how captions are drawn; the narration text, the WebVTT side
+//! file and the transcript are never altered.
+
+use crate::style::ass_colour;
+use serde::Deserialize;
+use std::collections::HashMap;
+
+/// How a keyword is emphasized.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct KeywordStyle {
+    /// Text colour as `#RRGGBB`
+    pub colour: Option<String>,
+    /// Size relative to the rest of the caption, in percent (ASS only)
+    pub scale: Option<u32>,
+    /// Emoji shown after the word
+    pub emoji: Option<String>,
+}
+
+/// A set of keywords and how to emphasize them.
+#[derive(Debug, Clone, Default)]
+pub struct Emphasis {
+    keywords: HashMap<String, KeywordStyle>,
+}
+
+/// Normalizes a caption word for keyword lookup.
+fn normalize(word: &str) -> String {
+    word.trim_matches(|c: char| !c.is_alphanumeric())
+        .to_lowercase()
+}
+
+impl Emphasis {
+    /// Builds the keyword set from the configuration file's `emphasis` map.
+    ///
+    /// # Arguments
+    /// * `keywords` - Map from keyword to its style
+    ///
+    /// # Returns
+    /// * `Ok(Emphasis)` - The keyword set
+    /// * `Err` - If a keyword is empty or a style has an invalid colour or scale
+    pub fn new(keywords: &HashMap<String, KeywordStyle>) -> anyhow::Result<Self> {
+        let mut normalized = HashMap::new();
+        for (keyword, style) in keywords {
+            let key = normalize(keyword);
+            anyhow::ensure!(
+                !key.is_empty(),
+                "Emphasis keyword {keyword:?} has no letters"
+            );
+            if let Some(colour) = &style.colour {
+                anyhow::ensure!(
+                    ass_colour(colour).is_some(),
+                    "Emphasis colour for {keyword:?} must be #RRGGBB, got {colour:?}"
+                );
+            }
+            anyhow::ensure!(
+                style.scale.is_none_or(|s| s > 0),
+                "Emphasis scale for {keyword:?} must be positive"
+            );
+            normalized.insert(key, style.clone());
+        }
+        Ok(Self {
+            keywords: normalized,
+        })
+    }
+
+    /// Number of keywords.
+    pub fn len(&self) -> usize {
+        self.keywords.len()
+    }
+
+    /// Whether there are no keywords.
+    pub fn is_empty(&self) -> bool {
+        self.keywords.is_empty()
+    }
+
+    /// Looks up the emphasis for a caption word.
+    ///
+    /// # Arguments
+    /// * `word` - Word as displayed, possibly with punctuation
+    ///
+    /// # Returns
+    /// * `Some(&KeywordStyle)` - If the word is a keyword
+    /// * `None` - Otherwise
+    pub fn lookup(&self, word: &str) -> Option<&KeywordStyle> {
+        if self.is_empty() {
+            return None;
+        }
+        self.keywords.get(&normalize(word))
+    }
+
+    /// Appends the emoji of every keyword in `text` after it.
+    ///
+    /// # Arguments
+    /// * `text` - Caption text
+    ///
+    /// # Returns
+    /// * `String` - The text with emoji added
+    pub fn with_emoji(&self, text: &str) -> String {
+        text.split_whitespace()
+            .flat_map(|word| {
+                let emoji = self.lookup(word).and_then(|s| s.emoji.as_deref());
+                std::iter::once(word).chain(emoji)
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::subtitle::{Cue, CueStyle, Track, write_srt};
+
+    fn emphasis() -> Emphasis {
+        let keyword = |colour: Option<&str>, emoji: Option<&str>| KeywordStyle {
+            colour: colour.map(str::to_string),
+            scale: None,
+            emoji: emoji.map(str::to_string),
+        };
+        Emphasis::new(&HashMap::from([
+            ("cheated".to_string(), keyword(Some("#FF0000"), None)),
+            ("Divorce".to_string(), keyword(Some("#FFD54F"), Some("💔"))),
+        ]))
+        .unwrap()
+    }
+
+    #[test]
+    fn shouted_and_quoted_keywords_are_detected() {
+        let emphasis = emphasis();
+        let shouted = emphasis.lookup("CHEATED!").unwrap();
+        assert_eq!(shouted.colour.as_deref(), Some("#FF0000"));
+        let quoted = emphasis.lookup("\"divorce\".").unwrap();
+        assert_eq!(quoted.emoji.as_deref(), Some("💔"));
+        assert!(emphasis.lookup("cheater").is_none());
+        assert!(emphasis.lookup("💔").is_none());
+    }
+
+    #[test]
+    fn srt_captions_colour_keywords_and_add_emoji() {
+        let text = "He CHEATED! I want a \"divorce\".";
+        let track = Track {
+            cues: vec![Cue {
+                start: 0.0,
+                end: 2.0,
+                text: text.to_string(),
+                words: Vec::new(),
+                speaker: None,
+                style: CueStyle::default(),
+                chunk: 0,
+            }],
+        };
+        let path = std::env::temp_dir().join(format!("emphasis_{}.srt", std::process::id()));
+        let path = path.to_str().unwrap();
+        write_srt(path, &track, 80, &emphasis()).unwrap();
+        let srt = std::fs::read_to_string(path).unwrap();
+        std::fs::remove_file(path).unwrap();
+        assert_eq!(
+            srt,
+            "1\n00:00:00,000 --> 00:00:02,000\n\
+             He <font color=\"#FF0000\">CHEATED!</font> I want a \
+             <font color=\"#FFD54F\">\"divorce\".</font> 💔\n\n"
+        );
+        // The cue text itself, which the narration and transcript use, is unchanged.
+        assert_eq!(track.cues[0].text, text);
+    }
+
+    #[test]
+    fn invalid_styles_are_rejected() {
+        let invalid = KeywordStyle {
+            colour: Some("red".to_string()),
+            ..KeywordStyle::default()
+        };
+        assert!(Emphasis::new(&HashMap::from([("nta".to_string(), invalid)])).is_err());
+        assert!(
+            Emphasis::new(&HashMap::from([(
+                "!!".to_string(),
+                KeywordStyle::default()
+            )]))
+            .is_err()
+        );
+    }
+}
@@ -0,0 +1,381 @@
+//! Typed construction of ffmpeg command lines.
+//!
+//! Commands are built from inputs, a filter graph and one output, then turned
+//! into an argument list. Filter arguments are escaped on both levels ffmpeg
+//! parses them (option values, then the filter graph), so paths containing
+//! `:`, `'`, `,` or brackets work. Building a command never runs anything, so
+//! the argument list can be inspected on its own.
+
+use std::fmt;
+use std::process::Command;
+use tracing::info;
+
+/// Escapes a filter option value (first level): `\`, `'` and `:`.
+fn escape_value(value: &str) -> String {
+    escape(value, &['\\', '\'', ':'])
+}
+
+/// Escapes a filter description inside a filter graph (second level).
+fn escape_description(description: &str) -> String {
+    escape(description, &['\\', '\'', '[', ']', ',', ';'])
+}
+
+fn escape(s: &str, special: &[char]) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        if special.contains(&c) {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
+
+/// Quotes an argument for display in a shell-style command line.
+fn shell_quote(arg: &str) -> String {
+    let plain = !arg.is_empty()
+        && arg
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./=:,+@%".contains(c));
+    if plain {
+        arg.to_string()
+    } else {
+        format!("'{}'", arg.replace('\'', r"'\''"))
+    }
+}
+
+/// One input file with the options that apply to it.
+#[derive(Debug, Clone)]
+pub struct Input {
+    path: String,
+    options: Vec<String>,
+}
+
+impl Input {
+    /// Creates an input reading `path`.
+    pub fn new(path: impl Into<String>) -> Self {
+        Self {
+            path: path.into(),
+            options: Vec::new(),
+        }
+    }
+
+    /// Starts reading at `seconds` into the file.
+    pub fn seek(self, seconds: f64) -> Self {
+        self.option("-ss", format!("{seconds:.3}"))
+    }
+
+    /// Repeats the input endlessly; the output duration must be limited.
+    pub fn looped(self) -> Self {
+        self.option("-stream_loop", "-1")
+    }
+
+    /// Adds an input option such as `-f` or `-loop`.
+    pub fn option(mut self, key: &str, value: impl Into<String>) -> Self {
+        self.options.push(key.to_string());
+        self.options.push(value.into());
+        self
+    }
+}
+
+/// A single filter such as `scale=1080:1920`.
+#[derive(Debug, Clone)]
+pub struct Filter {
+    name: String,
+    args: Vec<(Option<String>, String)>,
+}
+
+impl Filter {
+    /// Creates a filter without arguments.
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            args: Vec::new(),
+        }
+    }
+
+    /// Adds a positional argument.
+    pub fn arg(mut self, value: impl ToString) -> Self {
+        self.args.push((None, value.to_string()));
+        self
+    }
+
+    /// Adds a `key=value` argument.
+    pub fn opt(mut self, key: &str, value: impl ToString) -> Self {
+        self.args.push((Some(key.to_string()), value.to_string()));
+        self
+    }
+}
+
+impl fmt::Display for Filter {
+    /// Formats the filter as it appears in a filter graph, fully escaped.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name)?;
+        if self.args.is_empty() {
+            return Ok(());
+        }
+        let args = self
+            .args
+            .iter()
+            .map(|(key, value)| match key {
+                Some(key) => format!("{key}={}", escape_value(value)),
+                None => escape_value(value),
+            })
+            .collect::<Vec<_>>()
+            .join(":");
+        write!(f, "={}", escape_description(&args))
+    }
+}
+
+/// A linear chain of filters from labelled inputs to a labelled output.
+#[derive(Debug, Clone)]
+pub struct FilterChain {
+    inputs: Vec<String>,
+    filters: Vec<Filter>,
+    output: String,
+}
+
+impl FilterChain {
+    /// Creates a chain reading the given stream labels (e.g. `0:v`) and writing `output`.
+    pub fn new(inputs: &[&str], output: &str) -> Self {
+        Self {
+            inputs: inputs.iter().map(|s| s.to_string()).collect(),
+            filters: Vec::new(),
+            output: output.to_string(),
+        }
+    }
+
+    /// Appends a filter to the chain.
+    pub fn then(mut self, filter: Filter) -> Self {
+        self.filters.push(filter);
+        self
+    }
+}
+
+impl fmt::Display for FilterChain {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for input in &self.inputs {
+            write!(f, "[{input}]")?;
+        }
+        let filters = self
+            .filters
+            .iter()
+            .map(Filter::to_string)
+            .collect::<Vec<_>>();
+        let filters = if filters.is_empty() {
+            "null".to_string()
+        } else {
+            filters.join(",")
+        };
+        write!(f, "{filters}[{}]", self.output)
+    }
+}
+
+/// The single output file of a command.
+#[derive(Debug, Clone)]
+pub struct Output {
+    path: String,
+    options: Vec<String>,
+}
+
+impl Output {
+    /// Creates an output writing `path`.
+    pub fn new(path: impl Into<String>) -> Self {
+        Self {
+            path: path.into(),
+            options: Vec::new(),
+        }
+    }
+
+    /// Limits the output to `seconds`.
+    pub fn duration(self, seconds: f64) -> Self {
+        self.option("-t", format!("{seconds:.3}"))
+    }
+
+    /// Adds an output option such as `-c:v`.
+    pub fn option(mut self, key: &str, value: impl Into<String>) -> Self {
+        self.options.push(key.to_string());
+        self.options.push(value.into());
+        self
+    }
+
+    /// Adds an output flag without a value, such as `-shortest`.
+    pub fn flag(mut self, flag: &str) -> Self {
+        self.options.push(flag.to_string());
+        self
+    }
+
+    /// Adds a list of output options in order.
+    pub fn options(mut self, options: impl IntoIterator<Item = String>) -> Self {
+        self.options.extend(options);
+        self
+    }
+}
+
+/// A complete ffmpeg invocation.
+#[derive(Debug, Clone)]
+pub struct FfmpegCommand {
+    inputs: Vec<Input>,
+    graph: Vec<FilterChain>,
+    output: Output,
+    log_level: Option<String>,
+}
+
+impl FfmpegCommand {
+    /// Creates a command writing `output`, overwriting it if it exists.
+    pub fn new(output: Output) -> Self {
+        Self {
+            inputs: Vec::new(),
+            graph: Vec::new(),
+            output,
+            log_level: None,
+        }
+    }
+
+    /// Adds an input and returns its index for stream labels such as `0:v`.
+    pub fn input(&mut self, input: Input) -> usize {
+        self.inputs.push(input);
+        self.inputs.len() - 1
+    }
+
+    /// Selects a stream or filter graph label (e.g. `1:a:0` or `[v]`) for the output.
+    pub fn map(&mut self, stream: &str) -> &mut Self {
+        self.output
+            .options
+            .extend(["-map".to_string(), stream.to_string()]);
+        self
+    }
+
+    /// Adds a chain to the filter graph.
+    pub fn chain(&mut self, chain: FilterChain) -> &mut Self {
+        self.graph.push(chain);
+        self
+    }
+
+    /// Sets ffmpeg's `-loglevel`.
+    pub fn log_level(&mut self, level: &str) -> &mut Self {
+        self.log_level = Some(level.to_string());
+        self
+    }
+
+    /// The filter graph as passed to `-filter_complex`, or `None` if there is none.
+    pub fn filter_graph(&self) -> Option<String> {
+        (!self.graph.is_empty()).then(|| {
+            self.graph
+                .iter()
+                .map(FilterChain::to_string)
+                .collect::<Vec<_>>()
+                .join(";")
+        })
+    }
+
+    /// The arguments passed to ffmpeg, without the program name.
+    pub fn args(&self) -> Vec<String> {
+        let mut args = vec!["-y".to_string()];
+        if let Some(level) = &self.log_level {
+            args.extend(["-loglevel".to_string(), level.clone()]);
+        }
+        for input in &self.inputs {
+            args.extend(input.options.iter().cloned());
+            args.extend(["-i".to_string(), input.path.clone()]);
+        }
+        if let Some(graph) = self.filter_graph() {
+            args.extend(["-filter_complex".to_string(), graph]);
+        }
+        args.extend(self.output.options.iter().cloned());
+        args.push(self.output.path.clone());
+        args
+    }
+
+    /// The command line as it could be typed into a shell.
+    pub fn command_line(&self) -> String {
+        std::iter::once("ffmpeg".to_string())
+            .chain(self.args().iter().map(|a| shell_quote(a)))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// Logs and runs the command.
+    ///
+    /// # Arguments
+    /// * `what` - Description of the step, used in the error message
+    ///
+    /// # Returns
+    /// * `Ok(())` - If ffmpeg exited successfully
+    /// * `Err` - If ffmpeg could not be started or failed
+    pub fn run(&self, what: &str) -> anyhow::Result<()> {
+        info!("Running {}", self.command_line());
+        let status = Command::new("ffmpeg").args(self.args()).status()?;
+        if !status.success() {
+            anyhow::bail!("ffmpeg failed to {what} ({status})");
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn filter_values_are_escaped_on_both_levels() {
+        let filter = Filter::new("subtitles")
+            .opt("filename", "it's a:b,[c].srt")
+            .opt("force_style", "FontName=Arial,FontSize=24");
+        assert_eq!(
+            filter.to_string(),
+            r"subtitles=filename=it\\\'s a\\:b\,\[c\].srt:force_style=FontName=Arial\,FontSize=24"
+        );
+    }
+
+    #[test]
+    fn positional_arguments_and_bare_filters() {
+        assert_eq!(Filter::new("setsar").arg(1).to_string(), "setsar=1");
+        assert_eq!(Filter::new("null").to_string(), "null");
+        assert_eq!(
+            Filter::new("scale").arg(1080).arg(1920).to_string(),
+            "scale=1080:1920"
+        );
+    }
+
+    #[test]
+    fn empty_chain_passes_through() {
+        assert_eq!(FilterChain::new(&["0:v"], "v").to_string(), "[0:v]null[v]");
+    }
+
+    #[test]
+    fn command_arguments_are_ordered() {
+        let mut cmd = FfmpegCommand::new(Output::new("out.wav").flag("-vn").option("-ar", "48000"));
+        let input = cmd.input(Input::new("in.mp3").seek(1.5));
+        cmd.log_level("error").map(&format!("{input}:a"));
+        assert_eq!(
+            cmd.args(),
+            [
+                "-y",
+                "-loglevel",
+                "error",
+                "-ss",
+                "1.500",
+                "-i",
+                "in.mp3",
+                "-vn",
+                "-ar",
+                "48000",
+                "-map",
+                "0:a",
+                "out.wav"
+            ]
+        );
+        assert!(cmd.filter_graph().is_none());
+    }
+
+    #[test]
+    fn command_line_quotes_for_the_shell() {
+        let mut cmd = FfmpegCommand::new(Output::new("my video.mp4"));
+        cmd.input(Input::new("it's.mp4"));
+        assert_eq!(
+            cmd.command_line(),
+            r"ffmpeg -y -i 'it'\''s.mp4' 'my video.mp4'"
+        );
+    }
+}
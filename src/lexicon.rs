@@ -0,0 +1,178 @@
+//! User-editable pronunciation lexicon applied to text before synthesis.
+//!
+//! The lexicon file has one entry per line in the form `word = pronunciation`.
+//! A pronunciation is either a respelling (`Siobhan = shi-vawn`), a phoneme
+//! string in double brackets (`Nguyen = [[Nw'In]]`), or both separated by `|`
+//! (`Nguyen = win | [[Nw'In]]`). Phonemes are only sent to engines that
+//! accept inline phoneme input; other engines get the respelling, or the
+//! original word if there is none. Blank lines and lines starting with `#`
+//! are ignored. Subtitles always keep the original spelling.
+
+use regex::Regex;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use tracing::{info, warn};
+
+/// How a lexicon entry should be spoken.
+#[derive(Debug, Clone, Default)]
+pub struct Pronunciation {
+    /// Alternative spelling that the engine reads correctly
+    pub respelling: Option<String>,
+    /// Phoneme string for engines with inline phoneme support
+    pub phonemes: Option<String>,
+}
+
+/// A set of pronunciation overrides matched as whole words, case-insensitively.
+#[derive(Debug, Default)]
+pub struct Lexicon {
+    entries: HashMap<String, Pronunciation>,
+    pattern: Option<Regex>,
+}
+
+impl Lexicon {
+    /// Parses lexicon file contents.
+    ///
+    /// # Arguments
+    /// * `contents` - Lexicon text, one `word = pronunciation` entry per line
+    ///
+    /// # Returns
+    /// * `Ok(Lexicon)` - The parsed lexicon
+    /// * `Err` - If the combined match pattern cannot be compiled
+    pub fn parse(contents: &str) -> anyhow::Result<Self> {
+        let mut entries = HashMap::new();
+        for (n, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let Some((word, value)) = line.split_once('=') else {
+                warn!("Ignoring lexicon line {} without '=': {}", n + 1, line);
+                continue;
+            };
+            let mut pronunciation = Pronunciation::default();
+            for part in value.split('|').map(str::trim).filter(|p| !p.is_empty()) {
+                match part.strip_prefix("[[").and_then(|p| p.strip_suffix("]]")) {
+                    Some(phonemes) => pronunciation.phonemes = Some(phonemes.trim().to_string()),
+                    None => pronunciation.respelling = Some(part.to_string()),
+                }
+            }
+            entries.insert(word.trim().to_lowercase(), pronunciation);
+        }
+
+        let mut words: Vec<&String> = entries.keys().filter(|w| !w.is_empty()).collect();
+        // Longest first, so multi-word entries win over their parts.
+        words.sort_by_key(|w| std::cmp::Reverse(w.len()));
+        let pattern = if words.is_empty() {
+            None
+        } else {
+            let alternatives: Vec<String> = words.iter().map(|w| whole_word(w)).collect();
+            Some(Regex::new(&format!(r"(?i)(?:{})", alternatives.join("|")))?)
+        };
+        Ok(Self { entries, pattern })
+    }
+
+    /// Number of entries in the lexicon.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Rewrites `text` with the lexicon's pronunciations.
+    ///
+    /// # Arguments
+    /// * `text` - Text about to be synthesized
+    /// * `phonemes` - Whether the engine accepts inline `[[phonemes]]`
+    ///
+    /// # Returns
+    /// * `String` - The text to send to the TTS engine
+    pub fn apply(&self, text: &str, phonemes: bool) -> String {
+        let Some(pattern) = &self.pattern else {
+            return text.to_string();
+        };
+        pattern
+            .replace_all(text, |caps: &regex::Captures| {
+                let word = &caps[0];
+                let entry = &self.entries[&word.to_lowercase()];
+                match (&entry.phonemes, &entry.respelling) {
+                    (Some(p), _) if phonemes => format!("[[{p}]]"),
+                    (_, Some(respelling)) => respelling.clone(),
+                    _ => word.to_string(),
+                }
+            })
+            .into_owned()
+    }
+}
+
+/// Builds a pattern matching `word` only where it is not part of a longer word.
+///
+/// `\b` only works next to word characters: around `C#` or `.NET` it would
+/// require a letter after the `#` or before the `.`. Edges that are not word
+/// characters instead need `\B`, a neighbour that is not a word character
+/// either (or the start or end of the text).
+fn whole_word(word: &str) -> String {
+    let boundary = |c: Option<char>| match c {
+        Some(c) if c.is_alphanumeric() || c == '_' => r"\b",
+        _ => r"\B",
+    };
+    format!(
+        "{}{}{}",
+        boundary(word.chars().next()),
+        regex::escape(word),
+        boundary(word.chars().last())
+    )
+}
+
+/// Loads the pronunciation lexicon, returning an empty one if the file does not exist.
+///
+/// # Arguments
+/// * `path` - Path to the lexicon file
+///
+/// # Returns
+/// * `Ok(Lexicon)` - The loaded lexicon
+/// * `Err` - If the file exists but cannot be read or parsed
+pub fn load_lexicon(path: &str) -> anyhow::Result<Lexicon> {
+    if !Path::new(path).exists() {
+        return Ok(Lexicon::default());
+    }
+    let lexicon = Lexicon::parse(&fs::read_to_string(path)?)?;
+    info!(
+        "Loaded {} pronunciation entries from {}",
+        lexicon.len(),
+        path
+    );
+    Ok(lexicon)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lexicon() -> Lexicon {
+        Lexicon::parse("C# = see sharp\n.NET = dot net\nAITA = A I T A\nNguyen = win | [[ŋwˈɪn]]\n")
+            .unwrap()
+    }
+
+    #[test]
+    fn plain_words_match_whole_words_only() {
+        let lexicon = lexicon();
+        assert_eq!(lexicon.apply("aita? AITAH", false), "A I T A? AITAH");
+        assert_eq!(lexicon.apply("Mr Nguyen.", false), "Mr win.");
+        assert_eq!(lexicon.apply("Mr Nguyen.", true), "Mr [[ŋwˈɪn]].");
+    }
+
+    #[test]
+    fn entries_with_symbols_match() {
+        let lexicon = lexicon();
+        assert_eq!(
+            lexicon.apply("C# and .NET, (C#).", false),
+            "see sharp and dot net, (see sharp)."
+        );
+        assert_eq!(lexicon.apply(".net is fine", false), "dot net is fine");
+    }
+
+    #[test]
+    fn entries_with_symbols_do_not_match_inside_words() {
+        let lexicon = lexicon();
+        assert_eq!(lexicon.apply("ABC#x asp.NET", false), "ABC#x asp.NET");
+    }
+}
@@ -0,0 +1,346 @@
+//! Loudness measurement and normalization following EBU R128 / ITU-R BS.1770.
+//!
+//! Narration is K-weighted, measured with gated 400 ms blocks to get its
+//! integrated loudness, then amplified to a target loudness. A look-ahead
+//! limiter keeps the (approximated) true peak below a ceiling afterwards.
+
+use crate::audio::Pcm;
+use std::collections::VecDeque;
+use tracing::info;
+
+/// Length of a gating block in seconds.
+const BLOCK_SECONDS: f64 = 0.4;
+/// Step between gating blocks (75% overlap).
+const STEP_SECONDS: f64 = 0.1;
+/// Blocks quieter than this are ignored entirely.
+const ABSOLUTE_GATE_LUFS: f64 = -70.0;
+/// Blocks this far below the ungated loudness are ignored.
+const RELATIVE_GATE_LU: f64 = 10.0;
+/// True-peak oversampling factor.
+const OVERSAMPLING: usize = 4;
+/// Limiter look-ahead in seconds.
+const LOOKAHEAD_SECONDS: f64 = 0.005;
+/// Limiter release time in seconds.
+const RELEASE_SECONDS: f64 = 0.1;
+
+/// Loudness measurements before and after normalization.
+#[derive(Debug, Clone, Copy)]
+pub struct LoudnessReport {
+    /// Integrated loudness of the input in LUFS
+    pub input_lufs: f64,
+    /// True peak of the input in dBTP
+    pub input_peak_dbtp: f64,
+    /// Gain applied before limiting, in dB
+    pub gain_db: f64,
+    /// Integrated loudness of the output in LUFS
+    pub output_lufs: f64,
+    /// True peak of the output in dBTP
+    pub output_peak_dbtp: f64,
+}
+
+/// A direct-form I biquad filter section.
+struct Biquad {
+    b: [f64; 3],
+    a: [f64; 2],
+    x: [f64; 2],
+    y: [f64; 2],
+}
+
+impl Biquad {
+    fn process(&mut self, x0: f64) -> f64 {
+        let y0 = self.b[0] * x0 + self.b[1] * self.x[0] + self.b[2] * self.x[1]
+            - self.a[0] * self.y[0]
+            - self.a[1] * self.y[1];
+        self.x = [x0, self.x[0]];
+        self.y = [y0, self.y[0]];
+        y0
+    }
+}
+
+/// Builds the two K-weighting filter stages (high shelf, then high pass) for a sample rate.
+fn k_weighting(sample_rate: u32) -> [Biquad; 2] {
+    let fs = sample_rate as f64;
+
+    let (f0, gain_db, q) = (1681.974450955533, 3.999843853973347, 0.7071752369554196);
+    let k = (std::f64::consts::PI * f0 / fs).tan();
+    let vh = 10f64.powf(gain_db / 20.0);
+    let vb = vh.powf(0.4996667741545416);
+    let a0 = 1.0 + k / q + k * k;
+    let shelf = Biquad {
+        b: [
+            (vh + vb * k / q + k * k) / a0,
+            2.0 * (k * k - vh) / a0,
+            (vh - vb * k / q + k * k) / a0,
+        ],
+        a: [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+        x: [0.0; 2],
+        y: [0.0; 2],
+    };
+
+    let (f0, q) = (38.13547087602444, 0.5003270373238773);
+    let k = (std::f64::consts::PI * f0 / fs).tan();
+    let a0 = 1.0 + k / q + k * k;
+    let high_pass = Biquad {
+        b: [1.0, -2.0, 1.0],
+        a: [2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+        x: [0.0; 2],
+        y: [0.0; 2],
+    };
+    [shelf, high_pass]
+}
+
+/// Converts a channel-weighted mean square into LUFS.
+fn to_lufs(power: f64) -> f64 {
+    -0.691 + 10.0 * power.log10()
+}
+
+/// Measures integrated loudness in LUFS.
+///
+/// Mono and stereo channels are weighted equally, as BS.1770 specifies for
+/// front channels.
+///
+/// # Arguments
+/// * `pcm` - Audio to measure
+///
+/// # Returns
+/// * `f64` - Integrated loudness in LUFS, or negative infinity for silence
+pub fn integrated_loudness(pcm: &Pcm) -> f64 {
+    let channels = pcm.channels.max(1) as usize;
+    let frames = pcm.frames();
+    if frames == 0 {
+        return f64::NEG_INFINITY;
+    }
+
+    // K-weighted squared samples, summed across channels per frame.
+    let mut filters: Vec<[Biquad; 2]> = (0..channels)
+        .map(|_| k_weighting(pcm.sample_rate))
+        .collect();
+    let mut power = vec![0.0f64; frames];
+    for (frame, p) in pcm.samples.chunks_exact(channels).zip(power.iter_mut()) {
+        for (sample, [shelf, high_pass]) in frame.iter().zip(filters.iter_mut()) {
+            let y = high_pass.process(shelf.process(*sample as f64));
+            *p += y * y;
+        }
+    }
+
+    // Prefix sums make each overlapping block's mean square O(1).
+    let mut prefix = Vec::with_capacity(frames + 1);
+    prefix.push(0.0);
+    for p in &power {
+        prefix.push(prefix.last().unwrap() + p);
+    }
+    let block = ((BLOCK_SECONDS * pcm.sample_rate as f64) as usize).clamp(1, frames);
+    let step = ((STEP_SECONDS * pcm.sample_rate as f64) as usize).max(1);
+    let blocks: Vec<f64> = (0..=(frames - block) / step)
+        .map(|j| (prefix[j * step + block] - prefix[j * step]) / block as f64)
+        .collect();
+
+    let gated_mean = |threshold: f64| {
+        let kept: Vec<f64> = blocks
+            .iter()
+            .copied()
+            .filter(|&z| to_lufs(z) > threshold)
+            .collect();
+        if kept.is_empty() {
+            None
+        } else {
+            Some(kept.iter().sum::<f64>() / kept.len() as f64)
+        }
+    };
+    let Some(ungated) = gated_mean(ABSOLUTE_GATE_LUFS) else {
+        return f64::NEG_INFINITY;
+    };
+    let relative_gate = to_lufs(ungated) - RELATIVE_GATE_LU;
+    gated_mean(relative_gate.max(ABSOLUTE_GATE_LUFS)).map_or(f64::NEG_INFINITY, to_lufs)
+}
+
+/// Estimates the highest absolute inter-sample peak of each frame.
+///
+/// Samples are oversampled with Catmull-Rom interpolation between each frame
+/// and the next, which approximates the BS.1770 true-peak meter closely enough
+/// for limiting speech.
+fn frame_peaks(pcm: &Pcm) -> Vec<f32> {
+    let channels = pcm.channels.max(1) as usize;
+    let frames = pcm.frames();
+    let at =
+        |n: isize, c: usize| pcm.samples[(n.clamp(0, frames as isize - 1) as usize) * channels + c];
+    let mut peaks = vec![0.0f32; frames];
+    for (n, peak) in peaks.iter_mut().enumerate() {
+        let n = n as isize;
+        for c in 0..channels {
+            let (p0, p1, p2, p3) = (at(n - 1, c), at(n, c), at(n + 1, c), at(n + 2, c));
+            *peak = peak.max(p1.abs());
+            for k in 1..OVERSAMPLING {
+                let t = k as f32 / OVERSAMPLING as f32;
+                let v = 0.5
+                    * (2.0 * p1
+                        + (p2 - p0) * t
+                        + (2.0 * p0 - 5.0 * p1 + 4.0 * p2 - p3) * t * t
+                        + (3.0 * p1 - p0 - 3.0 * p2 + p3) * t * t * t);
+                *peak = peak.max(v.abs());
+            }
+        }
+    }
+    peaks
+}
+
+/// Converts a linear amplitude to decibels.
+fn to_db(amplitude: f32) -> f64 {
+    20.0 * (amplitude as f64).log10()
+}
+
+/// Measures the true peak in dBTP.
+///
+/// # Arguments
+/// * `pcm` - Audio to measure
+///
+/// # Returns
+/// * `f64` - Highest estimated inter-sample peak in dBTP
+pub fn true_peak_dbtp(pcm: &Pcm) -> f64 {
+    to_db(frame_peaks(pcm).into_iter().fold(0.0, f32::max))
+}
+
+/// Applies a look-ahead peak limiter so no true peak exceeds `ceiling`.
+///
+/// The gain needed to keep each frame under the ceiling is spread over the
+/// look-ahead window before the peak and recovers over the release time, so
+/// gain changes stay smooth. All channels share the same gain.
+fn limit(pcm: &mut Pcm, ceiling: f32) {
+    let channels = pcm.channels.max(1) as usize;
+    let frames = pcm.frames();
+    let lookahead = ((LOOKAHEAD_SECONDS * pcm.sample_rate as f64) as usize).max(1);
+    let release = 1.0 / (RELEASE_SECONDS * pcm.sample_rate as f64) as f32;
+
+    let required: Vec<f32> = frame_peaks(pcm)
+        .into_iter()
+        .map(|p| if p > ceiling { ceiling / p } else { 1.0 })
+        .collect();
+    if required.iter().all(|&g| g >= 1.0) {
+        return;
+    }
+
+    // Minimum required gain over the upcoming look-ahead window (a sliding
+    // minimum kept in a monotonic queue), with release.
+    let mut held = vec![1.0f32; frames];
+    let mut window: VecDeque<usize> = VecDeque::new();
+    let mut previous = 1.0f32;
+    for n in 0..frames + lookahead {
+        if n < frames {
+            while window.back().is_some_and(|&i| required[i] >= required[n]) {
+                window.pop_back();
+            }
+            window.push_back(n);
+        }
+        let Some(start) = n.checked_sub(lookahead) else {
+            continue;
+        };
+        while window.front().is_some_and(|&i| i < start) {
+            window.pop_front();
+        }
+        let window_min = window.front().map_or(1.0, |&i| required[i]);
+        previous = window_min.min(previous + release);
+        held[start] = previous;
+    }
+    // Averaging over the previous look-ahead frames ramps the gain down smoothly
+    // while never exceeding the required gain at a peak.
+    let mut sum = 0.0f32;
+    for n in 0..frames {
+        sum += held[n];
+        if n >= lookahead {
+            sum -= held[n - lookahead];
+        }
+        let gain = sum / (n + 1).min(lookahead) as f32;
+        for s in &mut pcm.samples[n * channels..(n + 1) * channels] {
+            *s *= gain;
+        }
+    }
+}
+
+/// Limits audio to a true-peak ceiling without changing its loudness otherwise.
+///
+/// Used after mixing music under normalized narration, where the music can
+/// push a few peaks over the ceiling.
+///
+/// # Arguments
+/// * `pcm` - Audio to limit in place
+/// * `ceiling_dbtp` - Maximum true peak after limiting, e.g. `-1.0`
+pub fn limit_true_peak(pcm: &mut Pcm, ceiling_dbtp: f64) {
+    limit(pcm, 10f64.powf(ceiling_dbtp / 20.0) as f32);
+}
+
+/// Normalizes audio to a target integrated loudness with a true-peak ceiling.
+///
+/// # Arguments
+/// * `pcm` - Audio to normalize in place
+/// * `target_lufs` - Target integrated loudness, e.g. `-14.0`
+/// * `ceiling_dbtp` - Maximum true peak after limiting, e.g. `-1.0`
+///
+/// # Returns
+/// * `LoudnessReport` - Measurements before and after; silent input is left unchanged
+pub fn normalize_loudness(pcm: &mut Pcm, target_lufs: f64, ceiling_dbtp: f64) -> LoudnessReport {
+    let input_lufs = integrated_loudness(pcm);
+    let input_peak_dbtp = true_peak_dbtp(pcm);
+    let gain_db = if input_lufs.is_finite() {
+        target_lufs - input_lufs
+    } else {
+        0.0
+    };
+
+    let gain = 10f64.powf(gain_db / 20.0) as f32;
+    for s in &mut pcm.samples {
+        *s *= gain;
+    }
+    limit_true_peak(pcm, ceiling_dbtp);
+
+    let report = LoudnessReport {
+        input_lufs,
+        input_peak_dbtp,
+        gain_db,
+        output_lufs: integrated_loudness(pcm),
+        output_peak_dbtp: true_peak_dbtp(pcm),
+    };
+    info!(
+        "Loudness: input {:.1} LUFS / {:.1} dBTP, gain {:+.1} dB, output {:.1} LUFS / {:.1} dBTP",
+        report.input_lufs,
+        report.input_peak_dbtp,
+        report.gain_db,
+        report.output_lufs,
+        report.output_peak_dbtp
+    );
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// One second of a 440 Hz tone at `amplitude`.
+    fn tone(amplitude: f32) -> Pcm {
+        let sample_rate = 16_000;
+        Pcm {
+            samples: (0..sample_rate)
+                .map(|n| {
+                    amplitude
+                        * (n as f32 * 440.0 * std::f32::consts::TAU / sample_rate as f32).sin()
+                })
+                .collect(),
+            sample_rate,
+            channels: 1,
+        }
+    }
+
+    #[test]
+    fn limiting_keeps_peaks_under_the_ceiling() {
+        let mut pcm = tone(1.0);
+        limit_true_peak(&mut pcm, -6.0);
+        assert!(true_peak_dbtp(&pcm) <= -5.9, "{}", true_peak_dbtp(&pcm));
+    }
+
+    #[test]
+    fn limiting_leaves_quiet_audio_alone() {
+        let mut pcm = tone(0.25);
+        let before = pcm.samples.clone();
+        limit_true_peak(&mut pcm, -1.0);
+        assert_eq!(pcm.samples, before);
+    }
+}
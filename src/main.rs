@@ -3,76 +3,203 @@
 //! This application fetches Reddit stories from specified subreddits and converts them
 //! into video content with text-to-speech narration and subtitles overlaid on a background video.
 
+mod align;
 mod args;
+mod ass;
 use clap::Parser;
 mod audio;
+mod background;
+mod cache;
+mod config;
+mod emphasis;
+mod ffmpeg;
+mod lexicon;
+mod loudness;
+mod metadata;
+mod music;
+mod output;
+mod parts;
+mod piper;
 mod reddit;
+mod style;
 mod subtitle;
+mod title_card;
 mod tts;
 mod utils;
 
-use crate::args::Args;
-use crate::reddit::fetch_reddit_story;
-use crate::subtitle::write_srt;
-use crate::tts::tts_generate_chunk;
-use crate::utils::chunk_text;
+use crate::args::{Args, Commands, SubtitleCommand};
+use crate::ass::{AssOptions, write_ass};
+use crate::audio::{SilenceOptions, TrimOptions};
+use crate::background::{
+    BackgroundClip, BackgroundSegment, choose_segment, pick_background, record_usage,
+};
+use crate::cache::TtsCache;
+use crate::config::{SubtitleConfig, load_config};
+use crate::emphasis::Emphasis;
+use crate::ffmpeg::{FfmpegCommand, Filter, FilterChain, Input, Output};
+use crate::lexicon::{Lexicon, load_lexicon};
+use crate::metadata::{
+    PartInfo, RunMetadata, SegmentTiming, part_path, read_background, read_title_card,
+    sidecar_path, write_metadata,
+};
+use crate::music::MusicOptions;
+use crate::output::{ResolvedOutput, resolve_output};
+use crate::reddit::{Story, fetch_reddit_story};
+use crate::style::{ResolvedStyle, resolve_style};
+use crate::subtitle::{
+    CaptionGrouping, GroupingOptions, SIDECAR_LINE_WIDTH, SubtitleFormat, Track, parse_subtitles,
+    write_srt, write_transcript, write_vtt,
+};
+use crate::title_card::{CardOverlay, TitleCard, resolve_title_card};
+use crate::tts::{SynthesizedChunk, Voice, build_voices, resolve_jobs, synthesize_with_retry};
+use crate::utils::{VoicedChunk, chunk_dialogue, chunk_text, correct_grammar};
 use std::fs;
-use std::fs::File;
-use std::io::Write;
 use std::path::Path;
-use std::process::Command;
+use std::sync::Arc;
+use tokio::sync::Semaphore;
 use tracing::{debug, error, info, warn};
 
 /// Main entry point for the Reddit stories video generator.
 ///
 /// This function orchestrates the entire pipeline:
 /// 1. Fetches a suitable Reddit story from the specified subreddit
-/// 2. Applies grammar correction to the story text
+/// 2. Applies grammar correction to the story title and body
 /// 3. Splits the text into manageable chunks for TTS processing
-/// 4. Generates audio files using Piper TTS for each chunk
+/// 4. Generates audio files with the configured TTS engine for each chunk
 /// 5. Creates subtitle files with proper timing
-/// 6. Combines audio chunks and merges with background video
-/// 7. Outputs the final video with embedded subtitles
+/// 6. Splits long stories into parts under `--max-duration`, if set
+/// 7. Combines audio chunks and merges with background video
+/// 8. Outputs each video with embedded subtitles and its run metadata
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     tracing_subscriber::fmt().with_env_filter("info").init();
 
     info!("Starting reddit story video generation pipeline");
     let args = Args::parse();
+    let cache_cap = args.tts_cache_max_mb * 1024 * 1024;
+    match &args.command {
+        Some(Commands::EvictCache { max_mb }) => {
+            let cap = max_mb.map_or(cache_cap, |mb| mb * 1024 * 1024);
+            let (files, bytes) = TtsCache::open(&args.tts_cache_dir)?.evict(cap)?;
+            info!("Removed {} cached chunks ({} bytes)", files, bytes);
+            return Ok(());
+        }
+        Some(Commands::Subtitles { action }) => return run_subtitle_command(&args, action),
+        None => {}
+    }
 
-    if !Path::new(&args.background).exists() {
-        error!("Background video not found: {}", args.background);
-        std::process::exit(1);
+    let config = load_config(&args.config)?;
+    let dialogue_profile = args
+        .dialogue_voice
+        .clone()
+        .or_else(|| config.tts.dialogue_profile.clone());
+    let mut voice_names = Vec::new();
+    if let Some(profile) = &dialogue_profile {
+        info!(
+            "Multi-voice narration enabled; dialogue voice '{}'",
+            profile
+        );
+        voice_names.push(profile.clone());
+        voice_names.extend(config.tts.character_profiles.values().cloned());
+    }
+    let title_profile = args
+        .title_voice
+        .clone()
+        .or_else(|| config.tts.title_profile.clone());
+    if let Some(profile) = &title_profile {
+        info!("Title narrated with voice '{}'", profile);
+        voice_names.push(profile.clone());
     }
-    info!("Background video found: {}", args.background);
+    let cache_dir = (!args.no_tts_cache).then_some(args.tts_cache_dir.as_str());
+    if let Some(dir) = cache_dir {
+        info!("Using TTS cache at {}", dir);
+    }
+    let output = resolve_output(&args, &config.output)?;
+    info!(
+        "Rendering with output profile '{}' ({}x{} at {} fps)",
+        output.name, output.width, output.height, output.fps
+    );
+    let subtitle_style = resolve_style(
+        &args,
+        &config.subtitles,
+        match args.subtitle_format {
+            SubtitleFormat::Srt => "classic",
+            SubtitleFormat::Ass => "bold",
+        },
+        &output,
+    )?;
+    let emphasis = load_emphasis(&args, &config.subtitles)?;
+    let title_card = resolve_title_card(&args, &config.title_card)?;
+    let voices = Arc::new(build_voices(&args, &config.tts, &voice_names, cache_dir)?);
+    let lexicon = Arc::new(load_lexicon(&args.lexicon)?);
+
+    let background_seed = args.background_seed.unwrap_or_else(|| fastrand::u64(..));
+    let clip = match pick_background(
+        &args.background,
+        &args.background_tag,
+        args.background_pick,
+        &args.background_usage,
+        background_seed,
+    ) {
+        Ok(clip) => clip,
+        Err(e) => {
+            error!("{e}");
+            std::process::exit(1);
+        }
+    };
+    info!("Background video found: {}", clip.path);
 
     info!(
         "Fetching reddit story from r/{} (up to {} posts, min {} chars)",
         args.subreddit, args.try_posts, args.min_chars
     );
-    let story = fetch_reddit_story(&args.subreddit, args.try_posts, args.min_chars).await?;
+    let mut story = fetch_reddit_story(&args.subreddit, args.try_posts, args.min_chars).await?;
+    info!("Using story: {}", story.title);
     info!(
-        "Using story (short preview): {:.200}",
-        story.replace('\n', " ")
+        "Story body (short preview): {:.200}",
+        story.body.replace('\n', " ")
     );
 
-    let story = match crate::utils::correct_grammar(&story).await {
-        Some(corrected) => {
-            info!("Grammar corrected.");
-            corrected
+    for text in [&mut story.title, &mut story.body] {
+        if text.trim().is_empty() {
+            continue;
         }
-        None => {
-            warn!("Grammar correction failed, using original text.");
-            story
+        match correct_grammar(text).await {
+            Some(corrected) => {
+                info!("Grammar corrected.");
+                *text = corrected;
+            }
+            None => warn!("Grammar correction failed, using original text."),
         }
-    };
+    }
 
-    let chunks = chunk_text(&story, args.chunk_chars);
+    let mut chunks: Vec<VoicedChunk> = chunk_text(&story.title, args.chunk_chars)
+        .into_iter()
+        .map(|text| VoicedChunk {
+            text,
+            voice: title_profile.clone(),
+        })
+        .collect();
+    let num_title_chunks = chunks.len();
+    if !story.body.trim().is_empty() {
+        chunks.extend(match &dialogue_profile {
+            Some(default_profile) => chunk_dialogue(&story.body, args.chunk_chars, |speaker| {
+                speaker
+                    .and_then(|s| config.tts.character_profiles.get(s))
+                    .unwrap_or(default_profile)
+                    .clone()
+            }),
+            None => chunk_text(&story.body, args.chunk_chars)
+                .into_iter()
+                .map(|text| VoicedChunk { text, voice: None })
+                .collect(),
+        });
+    }
     let num_chunks = chunks.len();
     info!("Split story into {} chunks", num_chunks);
     debug!(
         "First chunk preview: {}",
-        &chunks[0].chars().take(100).collect::<String>()
+        &chunks[0].text.chars().take(100).collect::<String>()
     );
 
     let tmp_dir = "rs_tmp";
@@ -83,131 +210,910 @@ async fn main() -> anyhow::Result<()> {
     fs::create_dir_all(tmp_dir)?;
     info!("Created tmp directory '{}'", tmp_dir);
 
+    let jobs = resolve_jobs(&args, &config.tts);
+    info!("Synthesizing with up to {} concurrent TTS jobs", jobs);
+    let semaphore = Arc::new(Semaphore::new(jobs));
     let mut tasks = Vec::new();
     for (i, chunk) in chunks.into_iter().enumerate() {
         let fname = format!("{tmp_dir}/part_{i:03}.wav");
-        let piper_model = args.piper_model.clone();
+        let voices = Arc::clone(&voices);
+        let lexicon = Arc::clone(&lexicon);
+        let semaphore = Arc::clone(&semaphore);
+        let retries = args.tts_retries;
         info!(
-            "Spawning TTS generation for chunk {}/{} ({} chars)",
+            "Queueing TTS generation for chunk {}/{} ({} chars, voice {})",
             i + 1,
             num_chunks,
-            chunk.len()
+            chunk.text.len(),
+            chunk.voice.as_deref().unwrap_or("narrator")
         );
         let task = tokio::task::spawn(async move {
-            match tts_generate_chunk(&piper_model, &chunk, &fname) {
-                Ok(_) => {
+            let _permit = semaphore.acquire_owned().await?;
+            let result = tokio::task::spawn_blocking({
+                let fname = fname.clone();
+                let chunk = chunk.clone();
+                let voices = Arc::clone(&voices);
+                move || {
+                    let engine = &voices.get(chunk.voice.as_deref()).engine;
+                    let spoken = lexicon.apply(&chunk.text, engine.supports_phonemes());
+                    synthesize_with_retry(engine.as_ref(), &spoken, &fname, retries)
+                }
+            })
+            .await?;
+            anyhow::Ok(match result {
+                Ok(timings) => {
                     info!("Finished TTS chunk {}: {}", i, fname);
-                    Ok((fname, chunk))
+                    Ok(SynthesizedChunk {
+                        path: fname,
+                        colour: voices.get(chunk.voice.as_deref()).colour.clone(),
+                        voice: chunk.voice,
+                        text: chunk.text,
+                        timings,
+                        is_title: i < num_title_chunks,
+                    })
                 }
                 Err(e) => {
-                    error!("Failed to generate TTS chunk {}: {:?}", i, e);
+                    error!("Failed to generate TTS chunk {}: {}", i, e);
                     Err(e)
                 }
-            }
+            })
         });
         tasks.push(task);
     }
 
     let mut tts_results = Vec::new();
-    for task in tasks {
-        let (fname, chunk) = task.await??;
-        tts_results.push((fname, chunk));
+    let mut failed = Vec::new();
+    for (i, task) in tasks.into_iter().enumerate() {
+        match task.await?? {
+            Ok(chunk) => tts_results.push(chunk),
+            Err(e) => failed.push((i, e)),
+        }
+    }
+    if !args.no_tts_cache {
+        TtsCache::open(&args.tts_cache_dir)?.evict(cache_cap)?;
     }
 
-    info!("Calculating WAV durations and building subtitles");
-    let srt_entries = subtitle::build_srt_entries(&tts_results)?;
+    if !failed.is_empty() {
+        error!("{} of {} TTS chunks failed:", failed.len(), num_chunks);
+        for (i, e) in &failed {
+            error!("  chunk {}: {}", i, e);
+        }
+        if !args.skip_failed_chunks || tts_results.is_empty() {
+            anyhow::bail!("TTS failed for {} chunks", failed.len());
+        }
+        warn!("Continuing without the failed chunks (--skip-failed-chunks)");
+    }
+
+    let silence = SilenceOptions {
+        threshold_db: args.silence_threshold,
+        ..SilenceOptions::default()
+    };
+    let trim = (!args.no_trim).then_some(TrimOptions {
+        silence,
+        pad_seconds: args.trim_pad,
+        max_pause: args.max_pause,
+    });
+    if let Some(options) = &trim {
+        let mut removed = 0.0;
+        for chunk in &mut tts_results {
+            removed += trim_chunk(chunk, options)?;
+        }
+        info!(
+            "Trimmed {:.2}s of silence from {} chunks",
+            removed,
+            tts_results.len()
+        );
+    }
 
-    let srt_path = format!("{tmp_dir}/subs.srt");
-    info!("Writing subtitles to {}", srt_path);
-    write_srt(&srt_path, &srt_entries)?;
+    let title_chunks = tts_results.iter().take_while(|c| c.is_title).count();
+    let mut title_block = title_chunks;
+    if title_chunks > 0 && title_chunks < tts_results.len() && args.title_pause > 0.0 {
+        let pause_path = format!("{tmp_dir}/title_pause.wav");
+        audio::write_silence_like(&tts_results[0].path, &pause_path, args.title_pause)?;
+        tts_results.insert(
+            title_chunks,
+            SynthesizedChunk {
+                path: pause_path,
+                text: String::new(),
+                timings: None,
+                voice: None,
+                colour: None,
+                is_title: false,
+            },
+        );
+        title_block += 1;
+    }
+
+    let music_track = args.music.as_deref().map(music::pick_music).transpose()?;
+    let context = VideoContext {
+        args: &args,
+        story: &story,
+        style: &subtitle_style,
+        emphasis: &emphasis,
+        output: &output,
+        title_card: title_card.as_ref(),
+        clip: &clip,
+        music_track: music_track.as_deref(),
+        silence,
+    };
 
-    let concat_list = format!("{tmp_dir}/files.txt");
-    let mut f = File::create(&concat_list)?;
-    for p in tts_results.iter().map(|(p, _)| p) {
-        let fname = Path::new(p)
-            .file_name()
-            .and_then(|n| n.to_str())
-            .ok_or_else(|| anyhow::anyhow!("Invalid filename"))?;
-        writeln!(f, "file '{fname}'")?;
+    let durations = tts_results
+        .iter()
+        .map(|c| audio::wav_duration_seconds(&c.path))
+        .collect::<anyhow::Result<Vec<f64>>>()?;
+    let total_seconds =
+        durations.iter().sum::<f64>() + args.chunk_gap * durations.len().saturating_sub(1) as f64;
+    // The title and the pause after it stay together; every other chunk is a unit of its own.
+    let unit_count = usize::from(title_block > 0) + tts_results.len() - title_block;
+    if let Some(max_seconds) = args.max_duration {
+        anyhow::ensure!(max_seconds > 0.0, "--max-duration must be positive");
+        if total_seconds > max_seconds && unit_count < 2 {
+            warn!(
+                "The {:.1}s story is longer than {:.1}s but has no chunk boundary to split at",
+                total_seconds, max_seconds
+            );
+        }
     }
-    info!("Created concat list file {}", concat_list);
+    match args.max_duration {
+        Some(max_seconds) if total_seconds > max_seconds && unit_count >= 2 => {
+            let mut units = Vec::new();
+            if title_block > 0 {
+                units.push(0..title_block);
+            }
+            units.extend((title_block..tts_results.len()).map(|i| i..i + 1));
+            let unit_seconds: Vec<f64> = units
+                .iter()
+                .map(|r| {
+                    durations[r.clone()].iter().sum::<f64>() + args.chunk_gap * (r.len() - 1) as f64
+                })
+                .collect();
+
+            let narrator = voices.get(None);
+            let mut announcements: Vec<(SynthesizedChunk, f64)> = Vec::new();
+            let plan =
+                parts::plan_parts(&unit_seconds, args.chunk_gap, max_seconds, &mut |number| {
+                    while announcements.len() < number {
+                        let chunk = announce_part(
+                            &args,
+                            narrator,
+                            &lexicon,
+                            trim.as_ref(),
+                            announcements.len() + 1,
+                            tmp_dir,
+                        )?;
+                        let seconds = audio::wav_duration_seconds(&chunk.path)?;
+                        announcements.push((chunk, seconds));
+                    }
+                    Ok(announcements[number - 1].1)
+                })?;
+            drop(voices);
+
+            let videos: Vec<String> = (1..=plan.len()).map(|n| part_path(&args.out, n)).collect();
+            info!(
+                "Splitting the {:.1}s story into {} parts of at most {:.1}s",
+                total_seconds,
+                plan.len(),
+                max_seconds
+            );
+            for (i, range) in plan.iter().enumerate() {
+                let number = i + 1;
+                let (announcement, announcement_seconds) = &announcements[i];
+                let seconds = announcement_seconds
+                    + unit_seconds[range.clone()]
+                        .iter()
+                        .map(|s| s + args.chunk_gap)
+                        .sum::<f64>();
+                if seconds > max_seconds {
+                    warn!(
+                        "Part {} is {:.1}s, longer than {:.1}s, because one chunk does not fit",
+                        number, seconds, max_seconds
+                    );
+                }
+                let mut chunks = vec![announcement.clone()];
+                chunks.extend_from_slice(
+                    &tts_results[units[range.start].start..units[range.end - 1].end],
+                );
+                let part_dir = format!("{tmp_dir}/part{number}");
+                fs::create_dir_all(&part_dir)?;
+                render_story_video(
+                    &context,
+                    &chunks,
+                    background_seed.wrapping_add(i as u64),
+                    Some(PartInfo {
+                        number,
+                        total: plan.len(),
+                        videos: videos.clone(),
+                    }),
+                    &videos[i],
+                    &part_dir,
+                )?;
+            }
+        }
+        _ => {
+            drop(voices);
+            render_story_video(
+                &context,
+                &tts_results,
+                background_seed,
+                None,
+                &args.out,
+                tmp_dir,
+            )?;
+        }
+    }
+    if Path::new(&args.background).is_dir() {
+        record_usage(&args.background_usage, &clip.path)?;
+    }
+
+    fs::remove_dir_all(tmp_dir)?;
+
+    info!("Process complete.");
+    Ok(())
+}
+
+/// Settings shared by every video rendered from one story.
+struct VideoContext<'a> {
+    /// Parsed command-line arguments
+    args: &'a Args,
+    /// The story being narrated
+    story: &'a Story,
+    /// Resolved subtitle style
+    style: &'a ResolvedStyle,
+    /// Keywords to emphasize in the captions
+    emphasis: &'a Emphasis,
+    /// Output profile with frame size and encoder settings
+    output: &'a ResolvedOutput,
+    /// Title card template, if enabled
+    title_card: Option<&'a TitleCard>,
+    /// Background clip
+    clip: &'a BackgroundClip,
+    /// Background music track mixed under the narration
+    music_track: Option<&'a Path>,
+    /// How silence in the chunks is detected
+    silence: SilenceOptions,
+}
+
+/// Trims silence from a synthesized chunk and moves its word timings to match.
+///
+/// # Arguments
+/// * `chunk` - Chunk to trim in place
+/// * `options` - Silence detection and padding settings
+///
+/// # Returns
+/// * `Ok(f64)` - Seconds of silence removed
+/// * `Err` - If the chunk's audio cannot be read or written
+fn trim_chunk(chunk: &mut SynthesizedChunk, options: &TrimOptions) -> anyhow::Result<f64> {
+    let trimmed = audio::trim_silence(&chunk.path, options)?;
+    for t in chunk.timings.iter_mut().flatten() {
+        t.start = trimmed.map_time(t.start);
+        t.end = trimmed.map_time(t.end);
+    }
+    Ok(trimmed.removed())
+}
+
+/// Synthesizes the announcement spoken at the start of a part, e.g. "Part 2".
+///
+/// # Arguments
+/// * `args` - Parsed command-line arguments
+/// * `voice` - Voice reading the announcement
+/// * `lexicon` - Pronunciation lexicon
+/// * `trim` - Silence trimming settings, if trimming is enabled
+/// * `number` - Part number, starting at 1
+/// * `tmp_dir` - Directory for the audio file
+///
+/// # Returns
+/// * `Ok(SynthesizedChunk)` - The announcement, captioned with its text
+/// * `Err` - If synthesis or trimming fails
+fn announce_part(
+    args: &Args,
+    voice: &Voice,
+    lexicon: &Lexicon,
+    trim: Option<&TrimOptions>,
+    number: usize,
+    tmp_dir: &str,
+) -> anyhow::Result<SynthesizedChunk> {
+    let text = args.part_announcement.replace("{n}", &number.to_string());
+    let path = format!("{tmp_dir}/announce_{number:03}.wav");
+    let spoken = lexicon.apply(&text, voice.engine.supports_phonemes());
+    let timings = synthesize_with_retry(voice.engine.as_ref(), &spoken, &path, args.tts_retries)?;
+    let mut chunk = SynthesizedChunk {
+        path,
+        text,
+        timings,
+        voice: None,
+        colour: voice.colour.clone(),
+        is_title: false,
+    };
+    if let Some(options) = trim {
+        trim_chunk(&mut chunk, options)?;
+    }
+    Ok(chunk)
+}
+
+/// Returns when the title chunks are read, excluding the pause after them.
+///
+/// # Arguments
+/// * `chunks` - Chunks in narration order
+/// * `gap_seconds` - Silence inserted between consecutive chunks
+///
+/// # Returns
+/// * `Ok(Some(SegmentTiming))` - Start and end of the title
+/// * `Ok(None)` - If there are no title chunks
+/// * `Err` - If a chunk's duration cannot be read
+fn title_timing(
+    chunks: &[SynthesizedChunk],
+    gap_seconds: f64,
+) -> anyhow::Result<Option<SegmentTiming>> {
+    let Some(first) = chunks.iter().position(|c| c.is_title) else {
+        return Ok(None);
+    };
+    let count = chunks[first..].iter().take_while(|c| c.is_title).count();
+    let mut start = gap_seconds * first as f64;
+    for chunk in &chunks[..first] {
+        start += audio::wav_duration_seconds(&chunk.path)?;
+    }
+    let mut end = start + gap_seconds * (count - 1) as f64;
+    for chunk in &chunks[first..first + count] {
+        end += audio::wav_duration_seconds(&chunk.path)?;
+    }
+    Ok(Some(SegmentTiming { start, end }))
+}
+
+/// Renders one video from synthesized chunks and writes its captions and metadata.
+///
+/// # Arguments
+/// * `ctx` - Settings shared by every video of the story
+/// * `chunks` - Chunks narrated in this video, in order
+/// * `seed` - Seed for the background start offset
+/// * `part` - Position among the parts, if the story is split
+/// * `out` - Output path of the video
+/// * `tmp_dir` - Directory for intermediate files
+///
+/// # Returns
+/// * `Ok(())` - If the video and its side files were written
+/// * `Err` - If audio processing, subtitles or ffmpeg fail
+fn render_story_video(
+    ctx: &VideoContext,
+    chunks: &[SynthesizedChunk],
+    seed: u64,
+    part: Option<PartInfo>,
+    out: &str,
+    tmp_dir: &str,
+) -> anyhow::Result<()> {
+    let args = ctx.args;
+    let title_timing = title_timing(chunks, args.chunk_gap)?;
+    if let Some(timing) = &title_timing {
+        info!(
+            "Title narrated from {:.2}s to {:.2}s",
+            timing.start, timing.end
+        );
+    }
+
+    info!("Calculating WAV durations and building subtitles");
+    let track = subtitle::build_track(
+        chunks,
+        args.chunk_gap,
+        &ctx.silence,
+        args.subtitle_alignment,
+    )?;
+    let words = track.cues.len();
+    let grouping = args.caption_grouping.unwrap_or(match args.subtitle_format {
+        SubtitleFormat::Srt => CaptionGrouping::Word,
+        SubtitleFormat::Ass => CaptionGrouping::Words,
+    });
+    let track = track.grouped(&GroupingOptions {
+        mode: grouping,
+        max_words: args.caption_words.max(1),
+        max_chars: args.caption_chars,
+        min_duration: args.min_caption_duration,
+    });
+    info!(
+        "Grouped {} subtitle words into {} captions ({} in dialogue or title voices)",
+        words,
+        track.cues.len(),
+        track.cues.iter().filter(|c| c.speaker.is_some()).count()
+    );
+
+    let subtitle_filter = write_burned_subtitles(args, ctx.style, ctx.emphasis, &track, tmp_dir)?;
 
     let combined_path = format!("{tmp_dir}/combined.wav");
     info!("Concatenating WAV chunks into one file {}", combined_path);
-
-    let status = Command::new("ffmpeg")
-        .current_dir(tmp_dir)
-        .args([
-            "-y",
-            "-f",
-            "concat",
-            "-safe",
-            "0",
-            "-i",
-            "files.txt",
-            "-c",
-            "copy",
-            "combined.wav",
-        ])
-        .status()?;
-
-    if !status.success() {
-        warn!("ffmpeg concat with copy failed; retrying with re-encode");
-        let status2 = Command::new("ffmpeg")
-            .current_dir(tmp_dir)
-            .args([
-                "-y",
-                "-f",
-                "concat",
-                "-safe",
-                "0",
-                "-i",
-                "files.txt",
-                "-c:a",
-                "pcm_s16le",
-                "combined.wav",
-            ])
-            .status()?;
-        if !status2.success() {
-            error!("ffmpeg failed to concatenate WAV files");
-            anyhow::bail!("ffmpeg failed to concatenate WAV files");
+    let parts: Vec<String> = chunks.iter().map(|c| c.path.clone()).collect();
+    audio::concat_wavs(&parts, &combined_path, args.chunk_gap)?;
+    if ctx.music_track.is_some() || !args.no_normalize {
+        let mut narration = audio::read_pcm(&combined_path)?;
+        // Normalize the voice on its own so the music bed does not lower it.
+        if !args.no_normalize {
+            info!(
+                "Normalizing narration to {} LUFS (true peak {} dBTP)",
+                args.target_lufs, args.true_peak
+            );
+            loudness::normalize_loudness(&mut narration, args.target_lufs, args.true_peak);
+        }
+        if let Some(track) = ctx.music_track {
+            info!("Mixing background music {}", track.display());
+            let bed = music::load_music(
+                track,
+                &format!("{tmp_dir}/music.wav"),
+                narration.sample_rate,
+                narration.channels,
+            )?;
+            music::mix_music(
+                &mut narration,
+                &bed,
+                &MusicOptions {
+                    volume_db: args.music_volume,
+                    duck_db: args.music_duck,
+                    speech_threshold_db: args.silence_threshold,
+                    fade_in: args.music_fade_in,
+                    fade_out: args.music_fade_out,
+                    ..MusicOptions::default()
+                },
+            );
+            if !args.no_normalize {
+                loudness::limit_true_peak(&mut narration, args.true_peak);
+            }
         }
+        audio::write_pcm(&combined_path, &narration)?;
     }
     info!("Combined audio written to {}", combined_path);
 
-    info!("Merging audio and subtitles into final video {}", &args.out);
-    let ff_args = [
-        "-y",
-        "-i",
-        &args.background,
-        "-i",
+    let narration_seconds = audio::wav_duration_seconds(&combined_path)?;
+    let background = choose_segment(ctx.clip, narration_seconds, seed)?;
+    let card_overlay = match (ctx.title_card, &title_timing) {
+        (Some(card), Some(timing)) => {
+            let path = sidecar_path(out, "card.png");
+            card.render(ctx.story, ctx.output.width, &path)?;
+            Some(CardOverlay {
+                path,
+                start: timing.start,
+                end: timing.end,
+            })
+        }
+        _ => None,
+    };
+
+    info!("Merging audio and subtitles into final video {}", out);
+    render_video(
+        &background,
         &combined_path,
-        "-vf",
-        &format!(
-            "scale=1080:1920,subtitles={srt_path}:force_style='Fontsize=28,OutlineColour=&H00C4903C&,Outline=3,Shadow=0,Alignment=10'"
-        ),
-        "-map",
-        "0:v:0",
-        "-map",
-        "1:a:0",
-        "-c:v",
-        "libx264",
-        "-c:a",
-        "aac",
-        "-r",
-        "60",
-        "-shortest",
-        &args.out,
-    ];
-    let status = Command::new("ffmpeg").args(ff_args).status()?;
-    if !status.success() {
-        error!("ffmpeg failed to produce final video");
-        anyhow::bail!("ffmpeg failed to produce final video");
-    }
-    info!("Final video written to {}", &args.out);
+        narration_seconds,
+        card_overlay.as_ref(),
+        subtitle_filter,
+        ctx.output,
+        out,
+    )?;
+    info!("Final video written to {}", out);
 
-    fs::remove_dir_all(tmp_dir)?;
+    let vtt_path = sidecar_path(out, "vtt");
+    write_vtt(&vtt_path, &track)?;
+    let transcript_path = sidecar_path(out, "transcript.json");
+    write_transcript(&transcript_path, &track)?;
+    info!("Captions written to {} and {}", vtt_path, transcript_path);
 
-    info!("Process complete.");
+    let story = ctx.story.clone();
+    write_metadata(
+        out,
+        &RunMetadata {
+            post_id: story.id,
+            subreddit: story.subreddit,
+            title: story.title,
+            author: story.author,
+            score: story.score,
+            num_comments: story.num_comments,
+            title_timing,
+            title_card: card_overlay.map(|card| card.path),
+            music: ctx.music_track.map(|t| t.to_string_lossy().into_owned()),
+            background,
+            part,
+        },
+    )
+}
+
+/// Loads the caption keyword emphasis from the configuration file.
+///
+/// # Arguments
+/// * `args` - Parsed command-line arguments
+/// * `subtitles` - Subtitle section of the configuration file
+///
+/// # Returns
+/// * `Ok(Emphasis)` - The keywords, or none with `--no-emphasis`
+/// * `Err` - If a keyword style is invalid
+fn load_emphasis(args: &Args, subtitles: &SubtitleConfig) -> anyhow::Result<Emphasis> {
+    if args.no_emphasis {
+        return Ok(Emphasis::default());
+    }
+    let emphasis = Emphasis::new(&subtitles.emphasis)?;
+    if !emphasis.is_empty() {
+        info!("Emphasizing {} caption keywords", emphasis.len());
+    }
+    Ok(emphasis)
+}
+
+/// Writes the subtitle file burned into the video and returns the ffmpeg filter that draws it.
+///
+/// # Arguments
+/// * `args` - Parsed command-line arguments
+/// * `style` - Resolved subtitle style
+/// * `emphasis` - Keywords to emphasize in the captions
+/// * `track` - Grouped subtitle track
+/// * `tmp_dir` - Directory for the subtitle file
+///
+/// # Returns
+/// * `Ok(Filter)` - The ffmpeg video filter
+/// * `Err` - If the subtitle file cannot be written
+fn write_burned_subtitles(
+    args: &Args,
+    style: &ResolvedStyle,
+    emphasis: &Emphasis,
+    track: &Track,
+    tmp_dir: &str,
+) -> anyhow::Result<Filter> {
+    let layout = style.line_layout();
+    let burned = if style.uppercase {
+        track.uppercased()
+    } else {
+        track.clone()
+    }
+    .fitted(&layout);
+    debug!(
+        "Captions wrap at {} columns, up to {} lines",
+        layout.width, layout.max_lines
+    );
+    let filter = match args.subtitle_format {
+        SubtitleFormat::Srt => {
+            let srt_path = format!("{tmp_dir}/subs.srt");
+            info!("Writing subtitles to {}", srt_path);
+            write_srt(&srt_path, &burned, layout.width, emphasis)?;
+            Filter::new("subtitles")
+                .opt("filename", &srt_path)
+                .opt("force_style", style.force_style())
+        }
+        SubtitleFormat::Ass => {
+            let ass_path = format!("{tmp_dir}/subs.ass");
+            info!("Writing karaoke subtitles to {}", ass_path);
+            let options = AssOptions::load(
+                args.ass_style.as_deref(),
+                style,
+                &args.highlight_colour,
+                emphasis.clone(),
+            )?;
+            write_ass(&ass_path, &burned, &options)?;
+            Filter::new("ass").opt("filename", &ass_path)
+        }
+    };
+    Ok(match &style.fonts_dir {
+        Some(dir) => filter.opt("fontsdir", dir),
+        None => filter,
+    })
+}
+
+/// Encodes the final video from a background segment, an audio track and a subtitle filter.
+///
+/// # Arguments
+/// * `background` - Background video and the segment of it to show
+/// * `audio` - File whose first audio stream is used
+/// * `duration` - Length of the audio in seconds
+/// * `title_card` - Title card to overlay while the title is read
+/// * `subtitle_filter` - ffmpeg filter drawing the subtitles
+/// * `output` - Output profile with frame size and encoder settings
+/// * `out` - Output path
+///
+/// # Returns
+/// * `Ok(())` - If ffmpeg succeeded
+/// * `Err` - If ffmpeg could not be run or failed
+fn render_video(
+    background: &BackgroundSegment,
+    audio: &str,
+    duration: f64,
+    title_card: Option<&CardOverlay>,
+    subtitle_filter: Filter,
+    output: &ResolvedOutput,
+    out: &str,
+) -> anyhow::Result<()> {
+    render_command(
+        background,
+        audio,
+        duration,
+        title_card,
+        subtitle_filter,
+        output,
+        out,
+    )
+    .run("produce the final video")
+}
+
+/// Builds the ffmpeg command that encodes the final video.
+///
+/// The background is scaled to cover the output frame and cropped, never
+/// stretched, and the video is exactly as long as the audio. Arguments are
+/// the same as for [`render_video`].
+fn render_command(
+    background: &BackgroundSegment,
+    audio: &str,
+    duration: f64,
+    title_card: Option<&CardOverlay>,
+    subtitle_filter: Filter,
+    output: &ResolvedOutput,
+    out: &str,
+) -> FfmpegCommand {
+    let mut cmd = FfmpegCommand::new(
+        Output::new(out)
+            .options(output.encoder_args())
+            .duration(duration),
+    );
+    let video = cmd.input(if background.looped {
+        Input::new(&background.path).looped()
+    } else {
+        Input::new(&background.path).seek(background.offset)
+    });
+    let audio = cmd.input(Input::new(audio));
+    cmd.chain(output.fill_filters().into_iter().fold(
+        FilterChain::new(&[&format!("{video}:v")], "bg"),
+        FilterChain::then,
+    ));
+    // The card goes under the captions so the title words stay readable.
+    let base = match title_card {
+        Some(card) => {
+            let image = cmd.input(Input::new(&card.path).option("-loop", "1"));
+            cmd.chain(
+                FilterChain::new(&["bg", &format!("{image}:v")], "carded").then(card.filter()),
+            );
+            "carded"
+        }
+        None => "bg",
+    };
+    cmd.chain(FilterChain::new(&[base], "v").then(subtitle_filter))
+        .map("[v]")
+        .map(&format!("{audio}:a:0"));
+    cmd
+}
+
+/// Runs one of the subtitle file tools.
+///
+/// # Arguments
+/// * `args` - Parsed command-line arguments
+/// * `action` - Subtitle tool to run
+///
+/// # Returns
+/// * `Ok(())` - If the tool succeeded
+/// * `Err` - If a file cannot be read or written, or validation fails
+fn run_subtitle_command(args: &Args, action: &SubtitleCommand) -> anyhow::Result<()> {
+    match action {
+        SubtitleCommand::Burn {
+            subtitles,
+            render,
+            output,
+        } => {
+            let track = parse_subtitles(subtitles)?;
+            track.validate()?;
+            let config = load_config(&args.config)?;
+            let profile = resolve_output(args, &config.output)?;
+            let style = resolve_style(
+                args,
+                &config.subtitles,
+                match args.subtitle_format {
+                    SubtitleFormat::Srt => "classic",
+                    SubtitleFormat::Ass => "bold",
+                },
+                &profile,
+            )?;
+            let emphasis = load_emphasis(args, &config.subtitles)?;
+            let duration = audio::media_duration_seconds(render)?;
+            // Reuse the render's background segment so only the captions change.
+            let background = match read_background(render) {
+                Some(segment) if Path::new(&segment.path).exists() => {
+                    info!(
+                        "Using the background segment recorded for {} ({} at {:.3}s)",
+                        render, segment.path, segment.offset
+                    );
+                    segment
+                }
+                _ => {
+                    let seed = args.background_seed.unwrap_or_else(|| fastrand::u64(..));
+                    let clip = pick_background(
+                        &args.background,
+                        &args.background_tag,
+                        args.background_pick,
+                        &args.background_usage,
+                        seed,
+                    )?;
+                    choose_segment(&clip, duration, seed)?
+                }
+            };
+            let tmp_dir = "rs_tmp_burn";
+            fs::create_dir_all(tmp_dir)?;
+            let burned = (|| {
+                let filter = write_burned_subtitles(args, &style, &emphasis, &track, tmp_dir)?;
+                info!(
+                    "Burning {} captions from {} with audio from {}",
+                    track.cues.len(),
+                    subtitles,
+                    render
+                );
+                let title_card =
+                    read_title_card(render).filter(|card| Path::new(&card.path).exists());
+                render_video(
+                    &background,
+                    render,
+                    duration,
+                    title_card.as_ref(),
+                    filter,
+                    &profile,
+                    output,
+                )
+            })();
+            // Clean up before reporting a failed step, so no temp dir is left behind.
+            if let Err(e) = fs::remove_dir_all(tmp_dir) {
+                warn!("Failed to remove {}: {}", tmp_dir, e);
+            }
+            burned?;
+            info!("Video written to {}", output);
+        }
+        SubtitleCommand::Retime {
+            input,
+            output,
+            shift,
+            scale,
+        } => {
+            anyhow::ensure!(*scale > 0.0, "Scale must be positive, got {scale}");
+            let track = parse_subtitles(input)?.retimed(*shift, *scale);
+            match Path::new(output).extension().and_then(|e| e.to_str()) {
+                Some(ext) if ext.eq_ignore_ascii_case("vtt") => write_vtt(output, &track)?,
+                Some(ext) if ext.eq_ignore_ascii_case("srt") => {
+                    write_srt(output, &track, SIDECAR_LINE_WIDTH, &Emphasis::default())?
+                }
+                _ => anyhow::bail!("Output must end in .srt or .vtt: {output}"),
+            }
+            info!(
+                "Re-timed {} captions (x{} {:+}s) into {}",
+                track.cues.len(),
+                scale,
+                shift,
+                output
+            );
+        }
+        SubtitleCommand::Validate { subtitles, audio } => {
+            let track = parse_subtitles(subtitles)?;
+            track.validate()?;
+            let end = track.cues.last().map_or(0.0, |c| c.end);
+            if let Some(audio) = audio {
+                let duration = audio::media_duration_seconds(audio)?;
+                anyhow::ensure!(
+                    end <= duration + 0.05,
+                    "Last caption ends at {:.3}s, after the audio ends at {:.3}s",
+                    end,
+                    duration
+                );
+                if duration - end > 2.0 {
+                    warn!(
+                        "Captions end {:.1}s before the audio; they may be cut short",
+                        duration - end
+                    );
+                }
+            }
+            info!(
+                "{} is valid: {} captions ending at {:.3}s",
+                subtitles,
+                track.cues.len(),
+                end
+            );
+        }
+    }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn short_output() -> ResolvedOutput {
+        ResolvedOutput {
+            name: "short".to_string(),
+            width: 1080,
+            height: 1920,
+            fps: 60,
+            crf: 20,
+            x264_preset: "medium".to_string(),
+            audio_bitrate: "192k".to_string(),
+            pix_fmt: "yuv420p".to_string(),
+        }
+    }
+
+    fn background(looped: bool) -> BackgroundSegment {
+        BackgroundSegment {
+            path: "res/bg.mp4".to_string(),
+            tags: Vec::new(),
+            offset: 12.5,
+            looped,
+            seed: 7,
+        }
+    }
+
+    #[test]
+    fn render_command_arguments() {
+        let cmd = render_command(
+            &background(false),
+            "rs_tmp/combined.wav",
+            61.25,
+            None,
+            Filter::new("ass").opt("filename", "rs_tmp/subs.ass"),
+            &short_output(),
+            "out.mp4",
+        );
+        let expected = [
+            "-y",
+            "-ss",
+            "12.500",
+            "-i",
+            "res/bg.mp4",
+            "-i",
+            "rs_tmp/combined.wav",
+            "-filter_complex",
+            "[0:v]scale=1080:1920:force_original_aspect_ratio=increase,crop=1080:1920,setsar=1[bg];\
+             [bg]ass=filename=rs_tmp/subs.ass[v]",
+            "-c:v",
+            "libx264",
+            "-preset",
+            "medium",
+            "-crf",
+            "20",
+            "-pix_fmt",
+            "yuv420p",
+            "-r",
+            "60",
+            "-c:a",
+            "aac",
+            "-b:a",
+            "192k",
+            "-t",
+            "61.250",
+            "-map",
+            "[v]",
+            "-map",
+            "1:a:0",
+            "out.mp4",
+        ];
+        assert_eq!(cmd.args(), expected);
+    }
+
+    #[test]
+    fn render_command_with_looped_background_and_title_card() {
+        let card = CardOverlay {
+            path: "out.card.png".to_string(),
+            start: 0.0,
+            end: 3.5,
+        };
+        let cmd = render_command(
+            &background(true),
+            "a.wav",
+            10.0,
+            Some(&card),
+            Filter::new("ass").opt("filename", "subs.ass"),
+            &short_output(),
+            "out.mp4",
+        );
+        let args = cmd.args();
+        assert_eq!(
+            &args[..10],
+            [
+                "-y",
+                "-stream_loop",
+                "-1",
+                "-i",
+                "res/bg.mp4",
+                "-i",
+                "a.wav",
+                "-loop",
+                "1",
+                "-i"
+            ]
+        );
+        assert_eq!(
+            cmd.filter_graph().unwrap(),
+            "[0:v]scale=1080:1920:force_original_aspect_ratio=increase,crop=1080:1920,setsar=1[bg];\
+             [bg][2:v]overlay=x=(W-w)/2:y=(H-h)/3:enable=between(t\\,0.000\\,3.500)[carded];\
+             [carded]ass=filename=subs.ass[v]"
+        );
+    }
+}
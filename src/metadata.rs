@@ -0,0 +1,156 @@
+//! Run metadata written next to the rendered video.
+//!
+//! The metadata file records which post was narrated and where key segments
+//! fall in the audio, so later stages and external tools can line up overlays
+//! with the narration.
+
+use crate::background::BackgroundSegment;
+use crate::title_card::CardOverlay;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+use tracing::info;
+
+/// Start and end of a narrated segment, in seconds from the start of the audio.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct SegmentTiming {
+    /// Start time in seconds
+    pub start: f64,
+    /// End time in seconds
+    pub end: f64,
+}
+
+/// Where a video sits in a story split into several parts.
+#[derive(Debug, Clone, Serialize)]
+pub struct PartInfo {
+    /// Number of this part, starting at 1
+    pub number: usize,
+    /// Number of parts the story was split into
+    pub total: usize,
+    /// Paths of every part's video, in order
+    pub videos: Vec<String>,
+}
+
+/// Metadata describing one rendered video.
+#[derive(Debug, Serialize)]
+pub struct RunMetadata {
+    /// Reddit post identifier
+    pub post_id: String,
+    /// Subreddit the story was taken from
+    pub subreddit: String,
+    /// Post title as narrated
+    pub title: String,
+    /// Username of the poster
+    pub author: String,
+    /// Net upvotes when the story was fetched
+    pub score: i64,
+    /// Number of comments when the story was fetched
+    pub num_comments: u64,
+    /// When the title is being read, excluding the pause after it
+    pub title_timing: Option<SegmentTiming>,
+    /// Title card image shown while the title is read
+    pub title_card: Option<String>,
+    /// Background music track mixed under the narration
+    pub music: Option<String>,
+    /// Background video and the segment of it shown
+    pub background: BackgroundSegment,
+    /// Position among the parts, if the story was split
+    pub part: Option<PartInfo>,
+}
+
+/// Returns the path of a file stored next to a video: the output path with another extension.
+///
+/// # Arguments
+/// * `out_path` - Path of the rendered video
+/// * `extension` - Extension of the side file, e.g. `vtt` or `transcript.json`
+///
+/// # Returns
+/// * `String` - Path of the side file
+pub fn sidecar_path(out_path: &str, extension: &str) -> String {
+    Path::new(out_path)
+        .with_extension(extension)
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// Returns the path of one part of a split video, e.g. `out_part2.mp4` for `out.mp4`.
+///
+/// # Arguments
+/// * `out_path` - Path given for the whole video
+/// * `number` - Part number, starting at 1
+///
+/// # Returns
+/// * `String` - Path of the part's video
+pub fn part_path(out_path: &str, number: usize) -> String {
+    let path = Path::new(out_path);
+    let stem = path
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let name = match path.extension() {
+        Some(ext) => format!("{stem}_part{number}.{}", ext.to_string_lossy()),
+        None => format!("{stem}_part{number}"),
+    };
+    path.with_file_name(name).to_string_lossy().into_owned()
+}
+
+/// Returns the metadata path for a video: the output path with a `.json` extension.
+///
+/// # Arguments
+/// * `out_path` - Path of the rendered video
+///
+/// # Returns
+/// * `String` - Path of the metadata file
+pub fn metadata_path(out_path: &str) -> String {
+    sidecar_path(out_path, "json")
+}
+
+/// Writes run metadata next to the rendered video.
+///
+/// # Arguments
+/// * `out_path` - Path of the rendered video
+/// * `metadata` - Metadata to write
+///
+/// # Returns
+/// * `Ok(())` - If the file was written
+/// * `Err` - If serialization or writing fails
+pub fn write_metadata(out_path: &str, metadata: &RunMetadata) -> anyhow::Result<()> {
+    let path = metadata_path(out_path);
+    fs::write(&path, serde_json::to_string_pretty(metadata)?)?;
+    info!("Run metadata written to {}", path);
+    Ok(())
+}
+
+/// Reads the background segment recorded in a video's metadata file.
+///
+/// # Arguments
+/// * `out_path` - Path of the rendered video
+///
+/// # Returns
+/// * `Some(BackgroundSegment)` - If the metadata file exists and records a background
+/// * `None` - Otherwise
+pub fn read_background(out_path: &str) -> Option<BackgroundSegment> {
+    let contents = fs::read_to_string(metadata_path(out_path)).ok()?;
+    let mut metadata: serde_json::Value = serde_json::from_str(&contents).ok()?;
+    serde_json::from_value(metadata.get_mut("background")?.take()).ok()
+}
+
+/// Reads the title card recorded in a video's metadata file.
+///
+/// # Arguments
+/// * `out_path` - Path of the rendered video
+///
+/// # Returns
+/// * `Some(CardOverlay)` - If the metadata records a title card and when the title is read
+/// * `None` - Otherwise
+pub fn read_title_card(out_path: &str) -> Option<CardOverlay> {
+    let contents = fs::read_to_string(metadata_path(out_path)).ok()?;
+    let metadata: serde_json::Value = serde_json::from_str(&contents).ok()?;
+    let timing: SegmentTiming =
+        serde_json::from_value(metadata.get("title_timing")?.clone()).ok()?;
+    Some(CardOverlay {
+        path: metadata.get("title_card")?.as_str()?.to_string(),
+        start: timing.start,
+        end: timing.end,
+    })
+}
@@ -0,0 +1,353 @@
+//! Background music mixed under the narration.
+//!
+//! A music track is decoded with ffmpeg to the narration's sample format,
+//! looped or cut to the narration's length, faded in and out, and ducked
+//! whenever the narration is speaking. Everything after decoding works on
+//! [`Pcm`] buffers, so the mixing stages can be exercised on synthetic signals.
+
+use crate::audio::{self, Pcm};
+use crate::ffmpeg::{FfmpegCommand, Input, Output};
+use crate::loudness::integrated_loudness;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::info;
+
+/// File extensions considered music when `--music` points at a directory.
+const MUSIC_EXTENSIONS: &[&str] = &["mp3", "wav", "ogg", "flac", "m4a", "aac", "opus"];
+/// Length of the crossfade where a looped track wraps around, in seconds.
+const LOOP_CROSSFADE_SECONDS: f64 = 0.05;
+/// Length of the RMS windows used to follow the narration, in milliseconds.
+const ENVELOPE_WINDOW_MS: f64 = 10.0;
+/// How long the music stays ducked after speech stops, in milliseconds.
+const DUCK_HOLD_MS: f64 = 250.0;
+
+/// Settings for mixing a music bed.
+#[derive(Debug, Clone, Copy)]
+pub struct MusicOptions {
+    /// Music loudness relative to the narration in dB (e.g. `-18.0`)
+    pub volume_db: f64,
+    /// Extra attenuation applied while the narration is speaking, in dB
+    pub duck_db: f64,
+    /// Narration level in dBFS above which it counts as speech
+    pub speech_threshold_db: f64,
+    /// Time for the music to duck once speech starts, in milliseconds
+    pub attack_ms: f64,
+    /// Time for the music to recover once speech stops, in milliseconds
+    pub release_ms: f64,
+    /// Fade-in at the start of the video, in seconds
+    pub fade_in: f64,
+    /// Fade-out at the end of the video, in seconds
+    pub fade_out: f64,
+}
+
+impl Default for MusicOptions {
+    fn default() -> Self {
+        Self {
+            volume_db: -18.0,
+            duck_db: 12.0,
+            speech_threshold_db: -40.0,
+            attack_ms: 50.0,
+            release_ms: 400.0,
+            fade_in: 2.0,
+            fade_out: 3.0,
+        }
+    }
+}
+
+/// Resolves the music track to use.
+///
+/// # Arguments
+/// * `path` - A music file, or a directory to pick a random track from
+///
+/// # Returns
+/// * `Ok(PathBuf)` - The chosen track
+/// * `Err` - If the path does not exist or the directory has no music files
+pub fn pick_music(path: &str) -> anyhow::Result<PathBuf> {
+    let path = Path::new(path);
+    if !path.is_dir() {
+        anyhow::ensure!(path.exists(), "Music file not found: {}", path.display());
+        return Ok(path.to_path_buf());
+    }
+    let mut tracks: Vec<PathBuf> = fs::read_dir(path)?
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|p| {
+            p.extension()
+                .and_then(|e| e.to_str())
+                .is_some_and(|e| MUSIC_EXTENSIONS.contains(&e.to_lowercase().as_str()))
+        })
+        .collect();
+    anyhow::ensure!(
+        !tracks.is_empty(),
+        "No music files found in {}",
+        path.display()
+    );
+    tracks.sort();
+    Ok(tracks.swap_remove(fastrand::usize(..tracks.len())))
+}
+
+/// Decodes a music file with ffmpeg into the given sample rate and channel count.
+///
+/// # Arguments
+/// * `path` - Music file in any format ffmpeg can read
+/// * `wav_path` - Where the decoded WAV is written
+/// * `sample_rate` - Target sample rate in Hz
+/// * `channels` - Target number of channels
+///
+/// # Returns
+/// * `Ok(Pcm)` - The decoded music
+/// * `Err` - If ffmpeg fails or its output cannot be read
+pub fn load_music(
+    path: &Path,
+    wav_path: &str,
+    sample_rate: u32,
+    channels: u16,
+) -> anyhow::Result<Pcm> {
+    let mut cmd = FfmpegCommand::new(
+        Output::new(wav_path)
+            .flag("-vn")
+            .option("-acodec", "pcm_s16le")
+            .option("-ar", sample_rate.to_string())
+            .option("-ac", channels.to_string()),
+    );
+    cmd.input(Input::new(path.to_string_lossy()));
+    cmd.log_level("error");
+    cmd.run(&format!("decode music {}", path.display()))?;
+    audio::read_pcm(wav_path)
+}
+
+/// Repeats or cuts audio to exactly `frames` frames.
+///
+/// Repetitions are joined with a short crossfade so the loop point does not click.
+///
+/// # Arguments
+/// * `pcm` - Audio to loop
+/// * `frames` - Length of the result in frames
+///
+/// # Returns
+/// * `Pcm` - Audio of the requested length; silence if `pcm` is empty
+pub fn loop_to_length(pcm: &Pcm, frames: usize) -> Pcm {
+    let channels = pcm.channels.max(1) as usize;
+    let len = pcm.frames();
+    if len == 0 {
+        return Pcm {
+            samples: vec![0.0; frames * channels],
+            ..pcm.clone()
+        };
+    }
+    let fade = ((LOOP_CROSSFADE_SECONDS * pcm.sample_rate as f64) as usize).min(len / 2);
+    let mut samples: Vec<f32> = Vec::with_capacity((frames + len) * channels);
+    while samples.len() < frames * channels {
+        let overlap = if samples.is_empty() { 0 } else { fade };
+        let start = samples.len() - overlap * channels;
+        for (i, &s) in pcm.samples[..len * channels].iter().enumerate() {
+            let frame = i / channels;
+            if frame < overlap {
+                let t = (frame + 1) as f32 / (overlap + 1) as f32;
+                let old = &mut samples[start + i];
+                *old = *old * (1.0 - t) + s * t;
+            } else {
+                samples.push(s);
+            }
+        }
+    }
+    samples.truncate(frames * channels);
+    Pcm {
+        samples,
+        ..pcm.clone()
+    }
+}
+
+/// Computes the music gain for each frame of the narration.
+///
+/// Windows where the narration is above the speech threshold pull the gain
+/// down to the ducked level; the gain stays there for a short hold time and
+/// moves between levels with separate attack and release times.
+///
+/// # Arguments
+/// * `narration` - The narration the music plays under
+/// * `options` - Ducking depth, threshold and timing
+///
+/// # Returns
+/// * `Vec<f32>` - Linear gain per narration frame, between the ducked level and `1.0`
+pub fn duck_gains(narration: &Pcm, options: &MusicOptions) -> Vec<f32> {
+    let window = audio::ms_to_frames(ENVELOPE_WINDOW_MS, narration.sample_rate);
+    let hold = (DUCK_HOLD_MS / ENVELOPE_WINDOW_MS).round() as usize;
+    let ducked = 10f64.powf(-options.duck_db.abs() / 20.0);
+    // One-pole coefficients per window reaching ~63% of a step in the given time.
+    let coefficient = |ms: f64| 1.0 - (-ENVELOPE_WINDOW_MS / ms.max(ENVELOPE_WINDOW_MS)).exp();
+    let (attack, release) = (
+        coefficient(options.attack_ms),
+        coefficient(options.release_ms),
+    );
+
+    let mut gains = Vec::new();
+    let mut gain = 1.0;
+    let mut since_speech = usize::MAX;
+    for level in audio::window_levels_db(narration, window) {
+        since_speech = if level >= options.speech_threshold_db {
+            0
+        } else {
+            since_speech.saturating_add(1)
+        };
+        let target = if since_speech <= hold { ducked } else { 1.0 };
+        let rate = if target < gain { attack } else { release };
+        gain += (target - gain) * rate;
+        gains.push(gain);
+    }
+
+    // Interpolate between window centres to get a smooth per-frame gain.
+    let frames = narration.frames();
+    (0..frames)
+        .map(|n| {
+            let pos = (n as f64 - window as f64 / 2.0) / window as f64;
+            let i = pos.floor().max(0.0) as usize;
+            let a = gains.get(i).copied().unwrap_or(1.0);
+            let b = gains.get(i + 1).copied().unwrap_or(a);
+            let t = (pos - i as f64).clamp(0.0, 1.0);
+            (a + (b - a) * t) as f32
+        })
+        .collect()
+}
+
+/// Applies linear fades to the start and end of audio.
+///
+/// # Arguments
+/// * `pcm` - Audio to fade in place
+/// * `fade_in` - Fade-in length in seconds
+/// * `fade_out` - Fade-out length in seconds
+pub fn apply_fades(pcm: &mut Pcm, fade_in: f64, fade_out: f64) {
+    let channels = pcm.channels.max(1) as usize;
+    let frames = pcm.frames();
+    let rate = pcm.sample_rate as f64;
+    let fade_in = ((fade_in.max(0.0) * rate) as usize).min(frames);
+    let fade_out = ((fade_out.max(0.0) * rate) as usize).min(frames);
+    for (n, frame) in pcm.samples.chunks_exact_mut(channels).enumerate() {
+        let mut gain = 1.0f32;
+        if n < fade_in {
+            gain *= n as f32 / fade_in as f32;
+        }
+        if n + fade_out >= frames && fade_out > 0 {
+            gain *= (frames - n - 1) as f32 / fade_out as f32;
+        }
+        for s in frame {
+            *s *= gain;
+        }
+    }
+}
+
+/// Mixes a music bed under the narration.
+///
+/// The music is levelled to `volume_db` relative to the narration's
+/// integrated loudness, looped to the narration's length, ducked under
+/// speech and faded in and out.
+///
+/// # Arguments
+/// * `narration` - Narration to mix into, in place
+/// * `music` - Music with the same sample rate and channel count as the narration
+/// * `options` - Level, ducking and fade settings
+pub fn mix_music(narration: &mut Pcm, music: &Pcm, options: &MusicOptions) {
+    let narration_lufs = integrated_loudness(narration);
+    let music_lufs = integrated_loudness(music);
+    let gain_db = if narration_lufs.is_finite() && music_lufs.is_finite() {
+        narration_lufs + options.volume_db - music_lufs
+    } else {
+        options.volume_db
+    };
+    info!(
+        "Music at {:+.1} dB, ducked by {:.1} dB under speech",
+        gain_db, options.duck_db
+    );
+
+    let mut bed = loop_to_length(music, narration.frames());
+    let level = 10f64.powf(gain_db / 20.0) as f32;
+    let gains = duck_gains(narration, options);
+    let channels = bed.channels.max(1) as usize;
+    for (frame, gain) in bed.samples.chunks_exact_mut(channels).zip(&gains) {
+        for s in frame {
+            *s *= level * gain;
+        }
+    }
+    apply_fades(&mut bed, options.fade_in, options.fade_out);
+
+    for (s, m) in narration.samples.iter_mut().zip(&bed.samples) {
+        *s += m;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_RATE: u32 = 16_000;
+
+    /// Builds mono audio from `(seconds, amplitude)` segments of a 220 Hz tone.
+    fn tone(segments: &[(f64, f32)]) -> Pcm {
+        let mut samples = Vec::new();
+        for &(seconds, amplitude) in segments {
+            let frames = (seconds * SAMPLE_RATE as f64) as usize;
+            samples.extend((0..frames).map(|n| {
+                let t = n as f32 / SAMPLE_RATE as f32;
+                (t * 220.0 * std::f32::consts::TAU).sin() * amplitude
+            }));
+        }
+        Pcm {
+            samples,
+            sample_rate: SAMPLE_RATE,
+            channels: 1,
+        }
+    }
+
+    fn at(gains: &[f32], seconds: f64) -> f32 {
+        gains[(seconds * SAMPLE_RATE as f64) as usize]
+    }
+
+    #[test]
+    fn gain_ducks_under_speech_and_recovers() {
+        let narration = tone(&[(1.0, 0.0), (1.0, 0.5), (2.0, 0.0)]);
+        let options = MusicOptions::default();
+        let gains = duck_gains(&narration, &options);
+        assert_eq!(gains.len(), narration.frames());
+        let ducked = 10f32.powf(-options.duck_db as f32 / 20.0);
+        assert!((at(&gains, 0.5) - 1.0).abs() < 1e-3);
+        assert!(
+            (at(&gains, 1.9) - ducked).abs() < 0.02,
+            "{}",
+            at(&gains, 1.9)
+        );
+        assert!(at(&gains, 3.9) > 0.95, "{}", at(&gains, 3.9));
+    }
+
+    #[test]
+    fn looped_clip_has_exact_length() {
+        let mut clip = tone(&[(0.1, 0.5)]);
+        clip.channels = 2;
+        for frames in [0, 300, 800, 3500] {
+            let looped = loop_to_length(&clip, frames);
+            assert_eq!(looped.frames(), frames);
+            assert_eq!(looped.samples.len(), frames * 2);
+        }
+    }
+
+    #[test]
+    fn empty_clip_loops_to_silence() {
+        let looped = loop_to_length(&tone(&[]), 100);
+        assert_eq!(looped.frames(), 100);
+        assert!(looped.samples.iter().all(|&s| s == 0.0));
+    }
+
+    #[test]
+    fn fades_start_and_end_at_zero() {
+        let mut pcm = Pcm {
+            samples: vec![1.0; 4 * SAMPLE_RATE as usize],
+            sample_rate: SAMPLE_RATE,
+            channels: 1,
+        };
+        apply_fades(&mut pcm, 1.0, 1.0);
+        let last = pcm.samples.len() - 1;
+        assert_eq!(pcm.samples[0], 0.0);
+        assert_eq!(pcm.samples[last], 0.0);
+        assert_eq!(pcm.samples[SAMPLE_RATE as usize], 1.0);
+        assert_eq!(pcm.samples[2 * SAMPLE_RATE as usize], 1.0);
+        assert!((pcm.samples[SAMPLE_RATE as usize / 2] - 0.5).abs() < 1e-3);
+    }
+}
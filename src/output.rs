@@ -0,0 +1,297 @@
+//! Output video profiles: frame size, frame rate and encoder settings.
+//!
+//! A profile starts from a named built-in or configured profile, is
+//! overridden by the `output` section of the configuration file and then by
+//! command-line options, the same way subtitle styles are resolved.
+
+use crate::args::Args;
+use crate::ffmpeg::Filter;
+use serde::Deserialize;
+use std::collections::HashMap;
+
+/// Speed presets accepted by libx264.
+const X264_PRESETS: &[&str] = &[
+    "ultrafast",
+    "superfast",
+    "veryfast",
+    "faster",
+    "fast",
+    "medium",
+    "slow",
+    "slower",
+    "veryslow",
+    "placebo",
+];
+
+/// Names of the built-in profiles.
+const PROFILES: &[&str] = &["short", "landscape", "square"];
+
+/// Output settings; unset values come from the profile.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct OutputProfile {
+    /// Frame size as `WIDTHxHEIGHT`
+    pub resolution: Option<String>,
+    /// Frames per second
+    pub fps: Option<u32>,
+    /// x264 constant rate factor (0-51, lower is better quality)
+    pub crf: Option<u8>,
+    /// x264 speed preset
+    pub x264_preset: Option<String>,
+    /// AAC audio bitrate, e.g. `192k`
+    pub audio_bitrate: Option<String>,
+    /// Pixel format, e.g. `yuv420p`
+    pub pix_fmt: Option<String>,
+}
+
+impl OutputProfile {
+    /// Returns this profile with every value set in `other` replaced.
+    fn overridden_by(self, other: &OutputProfile) -> Self {
+        let other = other.clone();
+        Self {
+            resolution: other.resolution.or(self.resolution),
+            fps: other.fps.or(self.fps),
+            crf: other.crf.or(self.crf),
+            x264_preset: other.x264_preset.or(self.x264_preset),
+            audio_bitrate: other.audio_bitrate.or(self.audio_bitrate),
+            pix_fmt: other.pix_fmt.or(self.pix_fmt),
+        }
+    }
+}
+
+/// Output section of the configuration file.
+///
+/// Values set directly in this section override the selected profile.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct OutputConfig {
+    /// Name of the profile to start from
+    pub profile: Option<String>,
+    /// Named profiles, layered over the built-in profile of the same name
+    pub profiles: HashMap<String, OutputProfile>,
+    /// Overrides applied on top of the profile
+    #[serde(flatten)]
+    pub settings: OutputProfile,
+}
+
+/// Returns a built-in profile with every value set.
+fn builtin_profile(name: &str) -> Option<OutputProfile> {
+    let profile = |resolution: &str| OutputProfile {
+        resolution: Some(resolution.to_string()),
+        fps: Some(60),
+        crf: Some(20),
+        x264_preset: Some("medium".to_string()),
+        audio_bitrate: Some("192k".to_string()),
+        pix_fmt: Some("yuv420p".to_string()),
+    };
+    match name {
+        // Vertical 9:16 for Shorts, Reels and TikTok.
+        "short" => Some(profile("1080x1920")),
+        "landscape" => Some(profile("1920x1080")),
+        "square" => Some(profile("1080x1080")),
+        _ => None,
+    }
+}
+
+/// A fully specified, validated output profile.
+#[derive(Debug, Clone)]
+pub struct ResolvedOutput {
+    /// Name of the profile the settings started from
+    pub name: String,
+    /// Frame width in pixels
+    pub width: u32,
+    /// Frame height in pixels
+    pub height: u32,
+    /// Frames per second
+    pub fps: u32,
+    /// x264 constant rate factor
+    pub crf: u8,
+    /// x264 speed preset
+    pub x264_preset: String,
+    /// AAC audio bitrate
+    pub audio_bitrate: String,
+    /// Pixel format
+    pub pix_fmt: String,
+}
+
+impl ResolvedOutput {
+    /// Video filters that scale the background to cover the frame and crop the overflow.
+    pub fn fill_filters(&self) -> Vec<Filter> {
+        vec![
+            Filter::new("scale")
+                .arg(self.width)
+                .arg(self.height)
+                .opt("force_original_aspect_ratio", "increase"),
+            Filter::new("crop").arg(self.width).arg(self.height),
+            Filter::new("setsar").arg(1),
+        ]
+    }
+
+    /// ffmpeg encoder options for this profile.
+    pub fn encoder_args(&self) -> Vec<String> {
+        [
+            "-c:v",
+            "libx264",
+            "-preset",
+            &self.x264_preset,
+            "-crf",
+            &self.crf.to_string(),
+            "-pix_fmt",
+            &self.pix_fmt,
+            "-r",
+            &self.fps.to_string(),
+            "-c:a",
+            "aac",
+            "-b:a",
+            &self.audio_bitrate,
+        ]
+        .map(String::from)
+        .to_vec()
+    }
+}
+
+/// Collects the output options given on the command line.
+fn cli_profile(args: &Args) -> OutputProfile {
+    OutputProfile {
+        resolution: args.resolution.clone(),
+        fps: args.fps,
+        crf: args.crf,
+        x264_preset: args.x264_preset.clone(),
+        audio_bitrate: args.audio_bitrate.clone(),
+        pix_fmt: args.pix_fmt.clone(),
+    }
+}
+
+/// Parses a `WIDTHxHEIGHT` frame size.
+fn parse_resolution(value: &str) -> Option<(u32, u32)> {
+    let (w, h) = value.trim().split_once(['x', 'X'])?;
+    Some((w.trim().parse().ok()?, h.trim().parse().ok()?))
+}
+
+/// Resolves the output profile from profiles, the configuration file and the command line.
+///
+/// # Arguments
+/// * `args` - Parsed command-line arguments
+/// * `config` - Output section of the configuration file
+///
+/// # Returns
+/// * `Ok(ResolvedOutput)` - The validated profile
+/// * `Err` - If the profile is unknown or a value is invalid
+pub fn resolve_output(args: &Args, config: &OutputConfig) -> anyhow::Result<ResolvedOutput> {
+    let name = args
+        .output_profile
+        .as_deref()
+        .or(config.profile.as_deref())
+        .unwrap_or("short");
+    let base = match builtin_profile(name) {
+        Some(profile) => profile,
+        None if config.profiles.contains_key(name) => builtin_profile("short").unwrap_or_default(),
+        None => anyhow::bail!(
+            "Unknown output profile: {name} (built in: {})",
+            PROFILES.join(", ")
+        ),
+    };
+    let mut profile = base;
+    if let Some(configured) = config.profiles.get(name) {
+        profile = profile.overridden_by(configured);
+    }
+    let profile = profile
+        .overridden_by(&config.settings)
+        .overridden_by(&cli_profile(args));
+
+    let resolution = profile.resolution.unwrap_or_default();
+    let Some((width, height)) = parse_resolution(&resolution) else {
+        anyhow::bail!("Output resolution must be WIDTHxHEIGHT, got {resolution:?}");
+    };
+    anyhow::ensure!(
+        width >= 2 && height >= 2 && width % 2 == 0 && height % 2 == 0,
+        "Output width and height must be even and at least 2, got {width}x{height}"
+    );
+    let fps = profile.fps.unwrap_or(60);
+    anyhow::ensure!(fps > 0, "Output frame rate must be positive");
+    let crf = profile.crf.unwrap_or(20);
+    anyhow::ensure!(crf <= 51, "CRF must be between 0 and 51, got {crf}");
+    let x264_preset = profile.x264_preset.unwrap_or_default();
+    anyhow::ensure!(
+        X264_PRESETS.contains(&x264_preset.as_str()),
+        "Unknown x264 preset {x264_preset:?} (expected one of {})",
+        X264_PRESETS.join(", ")
+    );
+    let audio_bitrate = profile.audio_bitrate.unwrap_or_default();
+    let digits = audio_bitrate
+        .strip_suffix(['k', 'K'])
+        .unwrap_or(&audio_bitrate);
+    anyhow::ensure!(
+        !digits.is_empty() && digits.chars().all(|c| c.is_ascii_digit()),
+        "Audio bitrate must be a number of bits or kilobits per second (e.g. 192k), got {audio_bitrate:?}"
+    );
+    let pix_fmt = profile.pix_fmt.unwrap_or_default();
+    anyhow::ensure!(
+        !pix_fmt.is_empty() && pix_fmt.chars().all(|c| c.is_ascii_alphanumeric()),
+        "Invalid pixel format {pix_fmt:?}"
+    );
+
+    Ok(ResolvedOutput {
+        name: name.to_string(),
+        width,
+        height,
+        fps,
+        crf,
+        x264_preset,
+        audio_bitrate,
+        pix_fmt,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    fn resolve(cli: &[&str], config: &str) -> anyhow::Result<ResolvedOutput> {
+        let args = Args::parse_from(std::iter::once("reddit-story").chain(cli.iter().copied()));
+        resolve_output(&args, &serde_json::from_str(config).unwrap())
+    }
+
+    #[test]
+    fn builtin_profiles_are_fully_specified() {
+        let short = resolve(&[], "{}").unwrap();
+        assert_eq!(short.name, "short");
+        assert_eq!(
+            (short.width, short.height, short.fps, short.crf),
+            (1080, 1920, 60, 20)
+        );
+        assert_eq!(short.encoder_args()[..2], ["-c:v", "libx264"]);
+        let square = resolve(&["--output-profile", "square"], "{}").unwrap();
+        assert_eq!((square.width, square.height), (1080, 1080));
+    }
+
+    #[test]
+    fn command_line_overrides_config_which_overrides_the_profile() {
+        let config = r#"{
+            "profile": "tiktok",
+            "profiles": {"tiktok": {"fps": 30, "crf": 23}},
+            "crf": 18,
+            "audio_bitrate": "128k"
+        }"#;
+        let output = resolve(&["--fps", "24", "--resolution", "720x1280"], config).unwrap();
+        assert_eq!(output.name, "tiktok");
+        // New profiles start from the short profile.
+        assert_eq!(output.x264_preset, "medium");
+        assert_eq!((output.width, output.height), (720, 1280));
+        assert_eq!(output.fps, 24);
+        assert_eq!(output.crf, 18);
+        assert_eq!(output.audio_bitrate, "128k");
+    }
+
+    #[test]
+    fn invalid_settings_are_rejected() {
+        let error = |cli: &[&str]| resolve(cli, "{}").unwrap_err().to_string();
+        assert!(error(&["--output-profile", "cinema"]).contains("Unknown output profile"));
+        assert!(error(&["--resolution", "1080by1920"]).contains("WIDTHxHEIGHT"));
+        assert!(error(&["--resolution", "1081x1920"]).contains("even"));
+        assert!(error(&["--crf", "52"]).contains("CRF"));
+        assert!(error(&["--x264-preset", "quick"]).contains("x264 preset"));
+        assert!(error(&["--audio-bitrate", "loud"]).contains("Audio bitrate"));
+    }
+}
@@ -0,0 +1,143 @@
+//! Splitting long stories into multi-part videos.
+//!
+//! Platforms cap Shorts and Reels at a minute or so, so a story longer than
+//! `--max-duration` is rendered as several videos. Parts are cut between
+//! narration chunks, which always end at a sentence, and the title is kept
+//! whole in the first part. Each part opens with a spoken "Part N"
+//! announcement, and parts are balanced so the last one is not a short stub.
+
+use std::ops::Range;
+
+/// Fills parts greedily with units, each part starting with its announcement.
+///
+/// A unit longer than `cap` on its own still gets a part to itself. Filling
+/// stops with `None` once more than `max_parts` parts would be needed, so no
+/// announcement beyond `max_parts` is ever requested.
+fn fill_parts(
+    units: &[f64],
+    gap: f64,
+    cap: f64,
+    max_parts: usize,
+    announcement: &mut dyn FnMut(usize) -> anyhow::Result<f64>,
+) -> anyhow::Result<Option<Vec<Range<usize>>>> {
+    let mut parts = Vec::new();
+    let mut start = 0;
+    while start < units.len() {
+        if parts.len() == max_parts {
+            return Ok(None);
+        }
+        let mut duration = announcement(parts.len() + 1)?;
+        let mut end = start;
+        while end < units.len() {
+            let next = duration + gap + units[end];
+            if end > start && next > cap {
+                break;
+            }
+            duration = next;
+            end += 1;
+        }
+        parts.push(start..end);
+        start = end;
+    }
+    Ok(Some(parts))
+}
+
+/// Groups narration units into as few parts as fit under a maximum duration.
+///
+/// Among the groupings with that number of parts, the one whose longest part
+/// is shortest is chosen, so parts come out about the same length.
+///
+/// # Arguments
+/// * `units` - Duration of each unit in seconds; a unit is never split
+/// * `gap` - Silence between consecutive chunks, in seconds
+/// * `max_seconds` - Longest a part should be, including its announcement
+/// * `announcement` - Returns the duration of the announcement for part `n` (1-based)
+///
+/// # Returns
+/// * `Ok(Vec<Range<usize>>)` - The units in each part, in order
+/// * `Err` - If an announcement cannot be produced
+pub fn plan_parts(
+    units: &[f64],
+    gap: f64,
+    max_seconds: f64,
+    announcement: &mut dyn FnMut(usize) -> anyhow::Result<f64>,
+) -> anyhow::Result<Vec<Range<usize>>> {
+    // Without a part limit filling always succeeds.
+    let parts = fill_parts(units, gap, max_seconds, usize::MAX, announcement)?.unwrap_or_default();
+    let target = parts.len();
+    if target <= 1 {
+        return Ok(parts);
+    }
+    // Shrink the cap while the number of parts stays the same. No part can be
+    // shorter than an even share of the units, so the search starts there.
+    let (mut lo, mut hi) = (units.iter().sum::<f64>() / target as f64, max_seconds);
+    let mut best = parts;
+    while hi - lo > 0.01 {
+        let mid = (lo + hi) / 2.0;
+        match fill_parts(units, gap, mid, target, announcement)? {
+            Some(parts) => {
+                best = parts;
+                hi = mid;
+            }
+            None => lo = mid,
+        }
+    }
+    Ok(best)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Sums the length of each part: its announcement plus its units and the gaps before them.
+    fn part_seconds(units: &[f64], gap: f64, announcement: f64, part: &Range<usize>) -> f64 {
+        announcement + units[part.clone()].iter().map(|u| u + gap).sum::<f64>()
+    }
+
+    #[test]
+    fn short_story_is_one_part() {
+        let parts = plan_parts(&[5.0, 5.0], 0.2, 60.0, &mut |_| Ok(1.0)).unwrap();
+        assert_eq!(parts, vec![0..2]);
+    }
+
+    #[test]
+    fn parts_are_balanced_and_under_the_limit() {
+        let units = [
+            6.0, 4.0, 5.0, 4.5, 5.0, 3.0, 6.0, 4.0, 5.0, 5.5, 4.0, 3.0, 2.0,
+        ];
+        let parts = plan_parts(&units, 0.2, 30.0, &mut |_| Ok(1.0)).unwrap();
+        assert_eq!(parts.len(), 3);
+        assert_eq!(parts.first().unwrap().start, 0);
+        assert_eq!(parts.last().unwrap().end, units.len());
+        for pair in parts.windows(2) {
+            assert_eq!(pair[0].end, pair[1].start);
+        }
+        let lengths: Vec<f64> = parts
+            .iter()
+            .map(|p| part_seconds(&units, 0.2, 1.0, p))
+            .collect();
+        assert!(lengths.iter().all(|&l| l <= 30.0), "{lengths:?}");
+        // Greedy filling would leave a 2s stub as the last part.
+        assert!(lengths.iter().all(|&l| l >= 15.0), "{lengths:?}");
+    }
+
+    #[test]
+    fn announcements_are_only_requested_for_used_parts() {
+        let units = vec![2.0; 40];
+        let mut requested = Vec::new();
+        let parts = plan_parts(&units, 0.2, 60.0, &mut |n| {
+            requested.push(n);
+            Ok(1.0)
+        })
+        .unwrap();
+        assert_eq!(parts.len(), 2);
+        assert!(requested.iter().all(|&n| n <= parts.len()), "{requested:?}");
+        assert!(requested.len() < 100, "{} calls", requested.len());
+    }
+
+    #[test]
+    fn oversized_unit_gets_its_own_part() {
+        let parts = plan_parts(&[40.0, 3.0], 0.2, 30.0, &mut |_| Ok(1.0)).unwrap();
+        assert_eq!(parts, vec![0..1, 1..2]);
+    }
+}
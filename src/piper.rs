@@ -0,0 +1,396 @@
+//! Piper TTS backend backed by a pool of long-lived Piper processes.
+//!
+//! Loading an ONNX voice model takes far longer than synthesizing a typical
+//! chunk, so instead of spawning one `piper` process per chunk this backend
+//! keeps up to `jobs` processes running in `--json-input` mode and feeds them
+//! one JSON request per line.
+
+use crate::config::{SpeakerRef, VoiceProfile};
+use crate::tts::{TtsEngine, TtsError, WordTiming};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs;
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+use std::sync::{Condvar, Mutex};
+use tracing::{debug, error, info, warn};
+
+/// Piper's built-in sentence silence, which is not stored in model configs.
+const DEFAULT_SENTENCE_SILENCE: f32 = 0.2;
+
+/// The parts of a Piper model's `.onnx.json` file used for voice setup.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct PiperModelConfig {
+    /// Number of speakers in the model
+    pub num_speakers: u32,
+    /// Speaker names mapped to their numeric ids
+    pub speaker_id_map: HashMap<String, u32>,
+    /// Default synthesis parameters recommended by the model
+    pub inference: PiperInference,
+    /// How the model's input is phonemized (`espeak` or `text`)
+    pub phoneme_type: Option<String>,
+}
+
+/// Default synthesis parameters from a Piper model config.
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct PiperInference {
+    /// Generator noise
+    pub noise_scale: f32,
+    /// Phoneme length multiplier
+    pub length_scale: f32,
+    /// Phoneme width noise
+    pub noise_w: f32,
+}
+
+impl Default for PiperInference {
+    fn default() -> Self {
+        Self {
+            noise_scale: 0.667,
+            length_scale: 1.0,
+            noise_w: 0.8,
+        }
+    }
+}
+
+/// Loads the `.onnx.json` config that Piper expects next to a model file.
+///
+/// # Arguments
+/// * `model` - Path to the Piper TTS model file (.onnx format)
+///
+/// # Returns
+/// * `Ok(Some(PiperModelConfig))` - The parsed model config
+/// * `Ok(None)` - If the model has no config file next to it
+/// * `Err` - If the config file exists but cannot be read or parsed
+pub fn load_model_config(model: &str) -> anyhow::Result<Option<PiperModelConfig>> {
+    let path = format!("{model}.json");
+    if !Path::new(&path).exists() {
+        return Ok(None);
+    }
+    let data = fs::read_to_string(&path)?;
+    let config = serde_json::from_str(&data)
+        .map_err(|e| anyhow::anyhow!("Invalid Piper model config {path}: {e}"))?;
+    Ok(Some(config))
+}
+
+/// Fully resolved Piper voice: a model plus its synthesis parameters.
+#[derive(Debug, Clone)]
+pub struct PiperVoice {
+    /// Path to the Piper TTS model file (.onnx format)
+    pub model: String,
+    /// Validated speaker id for multi-speaker models
+    pub speaker: Option<u32>,
+    /// Phoneme length multiplier
+    pub length_scale: f32,
+    /// Generator noise
+    pub noise_scale: f32,
+    /// Phoneme width noise
+    pub noise_w: f32,
+    /// Seconds of silence after each sentence
+    pub sentence_silence: f32,
+    /// Whether inline `[[phonemes]]` are passed through to Piper
+    pub phonemes: bool,
+}
+
+impl PiperVoice {
+    /// Resolves a voice profile against the model's config file.
+    ///
+    /// Parameters missing from the profile are taken from the model's
+    /// `inference` section, and the speaker is validated against the model's
+    /// speaker count and name map.
+    ///
+    /// Inline phoneme input is enabled when `phonemes` says so, or otherwise
+    /// when the model's config declares eSpeak phonemes, the only kind that
+    /// `[[...]]` blocks can be written in.
+    ///
+    /// # Arguments
+    /// * `model` - Path to the Piper TTS model file (.onnx format)
+    /// * `profile` - Requested voice parameters
+    /// * `phonemes` - Whether to pass `[[phonemes]]` to Piper, or `None` to detect it
+    ///
+    /// # Returns
+    /// * `Ok(PiperVoice)` - The resolved voice
+    /// * `Err` - If the model is missing or the speaker is unknown or out of range
+    pub fn resolve(
+        model: String,
+        profile: &VoiceProfile,
+        phonemes: Option<bool>,
+    ) -> anyhow::Result<Self> {
+        if !Path::new(&model).exists() {
+            return Err(TtsError::ModelMissing(model).into());
+        }
+        let model_config = load_model_config(&model)?;
+        if model_config.is_none() {
+            warn!("No config found for Piper model {model}; using Piper's default voice settings");
+        }
+        // Piper phonemizes with eSpeak unless the config says otherwise.
+        let espeak_phonemes = model_config
+            .as_ref()
+            .is_some_and(|c| c.phoneme_type.as_deref().unwrap_or("espeak") == "espeak");
+        let model_config = model_config.unwrap_or_default();
+
+        let speaker = match &profile.speaker {
+            None => None,
+            Some(SpeakerRef::Id(id)) => {
+                if model_config.num_speakers > 0 && *id >= model_config.num_speakers {
+                    anyhow::bail!(
+                        "Speaker id {id} out of range; model {model} has {} speakers",
+                        model_config.num_speakers
+                    );
+                }
+                Some(*id)
+            }
+            Some(SpeakerRef::Name(name)) => Some(
+                *model_config
+                    .speaker_id_map
+                    .get(name)
+                    .ok_or_else(|| anyhow::anyhow!("Unknown speaker {name} for model {model}"))?,
+            ),
+        };
+        if speaker.is_some() && model_config.num_speakers == 1 {
+            warn!("Model {model} has a single speaker; ignoring speaker selection");
+        }
+
+        let inference = &model_config.inference;
+        let voice = Self {
+            speaker: speaker.filter(|_| model_config.num_speakers != 1),
+            length_scale: profile.length_scale.unwrap_or(inference.length_scale),
+            noise_scale: profile.noise_scale.unwrap_or(inference.noise_scale),
+            noise_w: profile.noise_w.unwrap_or(inference.noise_w),
+            sentence_silence: profile.sentence_silence.unwrap_or(DEFAULT_SENTENCE_SILENCE),
+            phonemes: phonemes.unwrap_or(espeak_phonemes),
+            model,
+        };
+        info!(
+            "Piper voice: model={} speaker={:?} length_scale={} noise_scale={} noise_w={} sentence_silence={} phonemes={}",
+            voice.model,
+            voice.speaker,
+            voice.length_scale,
+            voice.noise_scale,
+            voice.noise_w,
+            voice.sentence_silence,
+            voice.phonemes
+        );
+        Ok(voice)
+    }
+
+    /// Command-line arguments passing this voice to a Piper process.
+    fn cli_args(&self) -> Vec<String> {
+        let mut args = vec![
+            "--model".to_string(),
+            self.model.clone(),
+            "--length_scale".to_string(),
+            self.length_scale.to_string(),
+            "--noise_scale".to_string(),
+            self.noise_scale.to_string(),
+            "--noise_w".to_string(),
+            self.noise_w.to_string(),
+            "--sentence_silence".to_string(),
+            self.sentence_silence.to_string(),
+        ];
+        if let Some(speaker) = self.speaker {
+            args.push("--speaker".to_string());
+            args.push(speaker.to_string());
+        }
+        args
+    }
+}
+
+/// A running Piper process waiting for JSON requests on stdin.
+struct PiperWorker {
+    child: Child,
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+}
+
+impl PiperWorker {
+    /// Starts a new Piper process in JSON input mode.
+    fn spawn(voice: &PiperVoice) -> Result<Self, TtsError> {
+        if !Path::new(&voice.model).exists() {
+            return Err(TtsError::ModelMissing(voice.model.clone()));
+        }
+        let mut child = Command::new("piper")
+            .args(voice.cli_args())
+            .arg("--json-input")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::inherit())
+            .spawn()
+            .map_err(|e| TtsError::from_spawn("piper", e))?;
+        let (Some(stdin), Some(stdout)) = (child.stdin.take(), child.stdout.take()) else {
+            return Err(std::io::Error::other("Failed to open piper stdio").into());
+        };
+        info!("Started piper worker (pid {})", child.id());
+        Ok(Self {
+            child,
+            stdin,
+            stdout: BufReader::new(stdout),
+        })
+    }
+
+    /// Sends one synthesis request and waits until Piper reports the written file.
+    fn synthesize(&mut self, text: &str, out_path: &str) -> Result<(), TtsError> {
+        let request = serde_json::json!({ "text": text, "output_file": out_path });
+        let sent = writeln!(self.stdin, "{request}").and_then(|_| self.stdin.flush());
+
+        let mut line = String::new();
+        let read = sent.and_then(|_| self.stdout.read_line(&mut line));
+        if matches!(read, Ok(0) | Err(_)) {
+            // The process died; report its exit status if it has one.
+            if let Ok(Some(status)) = self.child.try_wait()
+                && !status.success()
+            {
+                return Err(TtsError::NonZeroExit {
+                    program: "piper".to_string(),
+                    status,
+                });
+            }
+            return Err(read
+                .err()
+                .unwrap_or_else(|| {
+                    std::io::Error::other("piper closed its output before finishing the chunk")
+                })
+                .into());
+        }
+        debug!("piper wrote {}", line.trim());
+        Ok(())
+    }
+}
+
+impl Drop for PiperWorker {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// Idle workers and the number of live processes.
+struct PoolState {
+    idle: Vec<PiperWorker>,
+    live: usize,
+}
+
+/// Piper neural TTS using a bounded pool of persistent processes.
+pub struct PiperEngine {
+    voice: PiperVoice,
+    jobs: usize,
+    state: Mutex<PoolState>,
+    available: Condvar,
+}
+
+impl PiperEngine {
+    /// Creates a Piper engine; worker processes are started lazily on demand.
+    ///
+    /// # Arguments
+    /// * `voice` - Resolved Piper voice
+    /// * `jobs` - Maximum number of concurrent Piper processes
+    pub fn new(voice: PiperVoice, jobs: usize) -> Self {
+        Self {
+            voice,
+            jobs: jobs.max(1),
+            state: Mutex::new(PoolState {
+                idle: Vec::new(),
+                live: 0,
+            }),
+            available: Condvar::new(),
+        }
+    }
+
+    /// Takes an idle worker, starting a new one if the pool is not yet full,
+    /// or blocks until another chunk returns its worker.
+    fn checkout(&self) -> Result<PiperWorker, TtsError> {
+        let mut state = self.state.lock().unwrap();
+        loop {
+            if let Some(worker) = state.idle.pop() {
+                return Ok(worker);
+            }
+            if state.live < self.jobs {
+                state.live += 1;
+                drop(state);
+                return PiperWorker::spawn(&self.voice).inspect_err(|_| self.retire());
+            }
+            state = self.available.wait(state).unwrap();
+        }
+    }
+
+    /// Returns a healthy worker to the pool.
+    fn checkin(&self, worker: PiperWorker) {
+        self.state.lock().unwrap().idle.push(worker);
+        self.available.notify_one();
+    }
+
+    /// Forgets a worker that failed so a replacement can be started.
+    fn retire(&self) {
+        self.state.lock().unwrap().live -= 1;
+        self.available.notify_one();
+    }
+}
+
+impl TtsEngine for PiperEngine {
+    fn name(&self) -> &str {
+        "piper"
+    }
+
+    fn cache_key(&self) -> String {
+        let v = &self.voice;
+        format!(
+            "piper|{}|{:?}|{}|{}|{}|{}",
+            v.model, v.speaker, v.length_scale, v.noise_scale, v.noise_w, v.sentence_silence
+        )
+    }
+
+    fn supports_phonemes(&self) -> bool {
+        self.voice.phonemes
+    }
+
+    fn synthesize(&self, text: &str, out_path: &str) -> Result<Option<Vec<WordTiming>>, TtsError> {
+        let mut worker = self.checkout()?;
+        match worker.synthesize(text, out_path) {
+            Ok(()) => {
+                self.checkin(worker);
+                Ok(None)
+            }
+            Err(e) => {
+                error!("Piper TTS command failed for chunk: {}", out_path);
+                drop(worker);
+                self.retire();
+                Err(e)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Resolves a voice for a throwaway model whose config has `phoneme_type`.
+    fn resolve_with(phoneme_type: Option<&str>, phonemes: Option<bool>) -> PiperVoice {
+        let model = std::env::temp_dir().join(format!(
+            "piper_test_{}_{phoneme_type:?}_{phonemes:?}.onnx",
+            std::process::id()
+        ));
+        let model = model.to_string_lossy().into_owned();
+        fs::write(&model, b"").unwrap();
+        let config = match phoneme_type {
+            Some(kind) => format!(r#"{{"phoneme_type": "{kind}"}}"#),
+            None => "{}".to_string(),
+        };
+        fs::write(format!("{model}.json"), config).unwrap();
+        let voice = PiperVoice::resolve(model.clone(), &VoiceProfile::default(), phonemes).unwrap();
+        fs::remove_file(format!("{model}.json")).unwrap();
+        fs::remove_file(&model).unwrap();
+        voice
+    }
+
+    #[test]
+    fn phoneme_support_follows_the_model_config() {
+        assert!(resolve_with(None, None).phonemes);
+        assert!(resolve_with(Some("espeak"), None).phonemes);
+        assert!(!resolve_with(Some("text"), None).phonemes);
+        assert!(resolve_with(Some("text"), Some(true)).phonemes);
+        assert!(!resolve_with(Some("espeak"), Some(false)).phonemes);
+    }
+}
@@ -4,7 +4,7 @@
 //! specified subreddits, filters them based on content guidelines, and manages
 //! a history of used posts to avoid duplicates.
 
-use crate::utils::{correct_grammar, load_forbidden_words, sanitize_post};
+use crate::utils::{load_forbidden_words, sanitize_post};
 use reqwest::header::USER_AGENT;
 use serde::Deserialize;
 use std::collections::HashSet;
@@ -43,6 +43,31 @@ pub struct RedditPost {
     pub is_self: Option<bool>,
     /// Whether the post is marked as NSFW
     pub over_18: Option<bool>,
+    /// Username of the poster, without the `u/` prefix
+    pub author: Option<String>,
+    /// Net upvotes
+    pub score: Option<i64>,
+    /// Number of comments
+    pub num_comments: Option<u64>,
+}
+
+/// A story selected for narration, with title and body kept separate.
+#[derive(Debug, Clone)]
+pub struct Story {
+    /// Reddit post identifier
+    pub id: String,
+    /// Subreddit the story was taken from
+    pub subreddit: String,
+    /// Sanitized post title
+    pub title: String,
+    /// Sanitized post body (empty for title-only posts)
+    pub body: String,
+    /// Username of the poster
+    pub author: String,
+    /// Net upvotes when the story was fetched
+    pub score: i64,
+    /// Number of comments when the story was fetched
+    pub num_comments: u64,
 }
 
 /// Fetches a suitable Reddit story from the specified subreddit.
@@ -50,7 +75,7 @@ pub struct RedditPost {
 /// This function retrieves posts from Reddit's JSON API, filters them based on
 /// content guidelines (NSFW, forbidden words, length requirements), and returns
 /// the first suitable story found. It also maintains a history of used posts
-/// to avoid duplicates.
+/// to avoid duplicates. Grammar correction is left to the caller.
 ///
 /// # Arguments
 /// * `subreddit` - The subreddit name to fetch from (without 'r/' prefix)
@@ -58,13 +83,13 @@ pub struct RedditPost {
 /// * `min_chars` - Minimum character count required for a story
 ///
 /// # Returns
-/// * `Ok(String)` - The selected and processed story text
+/// * `Ok(Story)` - The selected and sanitized story
 /// * `Err` - If no suitable posts are found or API errors occur
 pub async fn fetch_reddit_story(
     subreddit: &str,
     limit: usize,
     min_chars: usize,
-) -> anyhow::Result<String> {
+) -> anyhow::Result<Story> {
     let url = format!("https://www.reddit.com/r/{subreddit}/hot.json?limit={limit}");
     let client = reqwest::Client::new();
     let res = client
@@ -105,11 +130,23 @@ pub async fn fetch_reddit_story(
             && !clean.trim().is_empty()
             && clean.chars().count() >= min_chars
         {
-            let corrected = correct_grammar(&clean).await.unwrap_or(clean.clone());
             info!("Selected post: {}", post.title);
             used_ids.insert(post.id.clone());
             save_used_ids(used_path, &used_ids)?;
-            return Ok(corrected);
+            let body = if is_self {
+                sanitize_post(&post.selftext, &[], usize::MAX).unwrap_or_default()
+            } else {
+                String::new()
+            };
+            return Ok(Story {
+                id: post.id,
+                subreddit: subreddit.to_string(),
+                title: sanitize_post(&post.title, &[], usize::MAX).unwrap_or_default(),
+                body,
+                author: post.author.unwrap_or_else(|| "[deleted]".to_string()),
+                score: post.score.unwrap_or(0),
+                num_comments: post.num_comments.unwrap_or(0),
+            });
         }
     }
     anyhow::bail!("No suitable posts found in subreddit {}", subreddit);
@@ -0,0 +1,433 @@
+//! Subtitle styling shared by the SRT and ASS outputs.
+//!
+//! A style starts from a named preset (built in, or defined in the
+//! configuration file), is overridden by the `subtitles` section of the
+//! configuration file and then by command-line options. Sizes and margins
+//! are in pixels of the output frame scaled to 1920 pixels tall, so a style
+//! looks the same at any resolution and wider frames only gain room across.
+//! Colours are `#RRGGBB`.
+
+use crate::args::Args;
+use crate::config::SubtitleConfig;
+use crate::output::ResolvedOutput;
+use crate::subtitle::LineLayout;
+use serde::Deserialize;
+use std::path::Path;
+
+/// Script width that the subtitles filter assumes for SRT input.
+const SRT_PLAY_RES_X: f64 = 384.0;
+/// Script height that the subtitles filter assumes for SRT input.
+const SRT_PLAY_RES_Y: f64 = 288.0;
+/// Script height that style sizes are given in.
+const STYLE_PLAY_RES_Y: f64 = 1920.0;
+/// Average advance of a glyph, as a fraction of the font size.
+const AVERAGE_GLYPH_WIDTH: f64 = 0.55;
+/// Average advance of an upper-case glyph, as a fraction of the font size.
+const AVERAGE_UPPERCASE_GLYPH_WIDTH: f64 = 0.7;
+
+/// Subtitle style settings; unset values come from the preset.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct SubtitleStyle {
+    /// Font family name
+    pub font: Option<String>,
+    /// Font file to load; its directory is passed to ffmpeg as the fonts directory
+    pub font_file: Option<String>,
+    /// Font size in pixels
+    pub size: Option<f64>,
+    /// Text colour as `#RRGGBB`
+    pub primary_colour: Option<String>,
+    /// Outline colour as `#RRGGBB`
+    pub outline_colour: Option<String>,
+    /// Outline width in pixels
+    pub outline: Option<f64>,
+    /// Shadow distance in pixels
+    pub shadow: Option<f64>,
+    /// Bold text
+    pub bold: Option<bool>,
+    /// Position on screen as a numpad digit (1 = bottom left, 5 = centre, 9 = top right)
+    pub position: Option<u8>,
+    /// Left margin in pixels
+    pub margin_l: Option<u32>,
+    /// Right margin in pixels
+    pub margin_r: Option<u32>,
+    /// Vertical margin in pixels
+    pub margin_v: Option<u32>,
+    /// Show captions in upper case
+    pub uppercase: Option<bool>,
+    /// Most lines per caption; longer captions are split
+    pub max_lines: Option<usize>,
+}
+
+impl SubtitleStyle {
+    /// Returns this style with every value set in `other` replaced.
+    fn overridden_by(self, other: &SubtitleStyle) -> Self {
+        let other = other.clone();
+        Self {
+            font: other.font.or(self.font),
+            font_file: other.font_file.or(self.font_file),
+            size: other.size.or(self.size),
+            primary_colour: other.primary_colour.or(self.primary_colour),
+            outline_colour: other.outline_colour.or(self.outline_colour),
+            outline: other.outline.or(self.outline),
+            shadow: other.shadow.or(self.shadow),
+            bold: other.bold.or(self.bold),
+            position: other.position.or(self.position),
+            margin_l: other.margin_l.or(self.margin_l),
+            margin_r: other.margin_r.or(self.margin_r),
+            margin_v: other.margin_v.or(self.margin_v),
+            uppercase: other.uppercase.or(self.uppercase),
+            max_lines: other.max_lines.or(self.max_lines),
+        }
+    }
+}
+
+/// Names of the built-in presets.
+const PRESETS: &[&str] = &["classic", "bold", "minimal"];
+
+/// Returns a built-in preset with every value set.
+fn builtin_preset(name: &str) -> Option<SubtitleStyle> {
+    let preset =
+        |size, outline_colour: &str, outline, shadow, bold, position, margin_v, uppercase| {
+            SubtitleStyle {
+                font: Some("Arial".to_string()),
+                font_file: None,
+                size: Some(size),
+                primary_colour: Some("#FFFFFF".to_string()),
+                outline_colour: Some(outline_colour.to_string()),
+                outline: Some(outline),
+                shadow: Some(shadow),
+                bold: Some(bold),
+                position: Some(position),
+                margin_l: Some(60),
+                margin_r: Some(60),
+                margin_v: Some(margin_v),
+                uppercase: Some(uppercase),
+                max_lines: Some(2),
+            }
+        };
+    match name {
+        // The original look: large centred words with a blue outline.
+        "classic" => Some(preset(187.0, "#3C90C4", 20.0, 0.0, false, 5, 0, false)),
+        "bold" => Some(preset(96.0, "#000000", 8.0, 0.0, true, 5, 0, true)),
+        "minimal" => Some(preset(72.0, "#000000", 4.0, 2.0, false, 2, 320, false)),
+        _ => None,
+    }
+}
+
+/// A fully specified, validated subtitle style.
+#[derive(Debug, Clone)]
+pub struct ResolvedStyle {
+    /// Font family name
+    pub font: String,
+    /// Directory to load fonts from
+    pub fonts_dir: Option<String>,
+    /// Font size in pixels
+    pub size: f64,
+    /// Text colour as `#RRGGBB`
+    pub primary_colour: String,
+    /// Outline colour as `#RRGGBB`
+    pub outline_colour: String,
+    /// Outline width in pixels
+    pub outline: f64,
+    /// Shadow distance in pixels
+    pub shadow: f64,
+    /// Bold text
+    pub bold: bool,
+    /// Numpad position, 1 to 9
+    pub position: u8,
+    /// Left margin in pixels
+    pub margin_l: u32,
+    /// Right margin in pixels
+    pub margin_r: u32,
+    /// Vertical margin in pixels
+    pub margin_v: u32,
+    /// Show captions in upper case
+    pub uppercase: bool,
+    /// Most lines per caption
+    pub max_lines: usize,
+    /// Width of the output frame in style pixels
+    pub play_res_x: f64,
+}
+
+/// Converts `#RRGGBB` into an ASS colour (`&HBBGGRR&`).
+///
+/// # Arguments
+/// * `hex` - Colour as `#RRGGBB`
+///
+/// # Returns
+/// * `Some(String)` - The ASS colour
+/// * `None` - If `hex` is not a valid `#RRGGBB` colour
+pub fn ass_colour(hex: &str) -> Option<String> {
+    let hex = hex.strip_prefix('#')?;
+    if hex.len() != 6 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    Some(format!("&H{}{}{}&", &hex[4..6], &hex[2..4], &hex[0..2]).to_uppercase())
+}
+
+impl ResolvedStyle {
+    /// Estimates how many display columns fit between the margins at this font size.
+    pub fn line_layout(&self) -> LineLayout {
+        let glyph = if self.uppercase {
+            AVERAGE_UPPERCASE_GLYPH_WIDTH
+        } else {
+            AVERAGE_GLYPH_WIDTH
+        };
+        let usable = self.play_res_x - (self.margin_l + self.margin_r) as f64;
+        LineLayout {
+            width: (usable / (self.size * glyph)).floor().max(1.0) as usize,
+            max_lines: self.max_lines,
+        }
+    }
+
+    /// Style fields for the ffmpeg `subtitles` filter's `force_style` option.
+    ///
+    /// Sizes are rescaled to the script resolution ffmpeg uses for SRT files.
+    /// libass reads `force_style` alignment in the old SSA numbering, where the
+    /// middle row is 9 to 11 and the top row 5 to 7.
+    pub fn force_style(&self) -> String {
+        let alignment = match self.position {
+            4..=6 => self.position + 5,
+            7..=9 => self.position - 2,
+            bottom => bottom,
+        };
+        let scale = SRT_PLAY_RES_Y / STYLE_PLAY_RES_Y;
+        let scale_x = SRT_PLAY_RES_X / self.play_res_x;
+        format!(
+            "FontName={},Fontsize={:.0},PrimaryColour={},OutlineColour={},Outline={:.1},Shadow={:.1},Bold={},Alignment={},MarginL={:.0},MarginR={:.0},MarginV={:.0}",
+            self.font,
+            self.size * scale,
+            ass_colour(&self.primary_colour).unwrap_or_default(),
+            ass_colour(&self.outline_colour).unwrap_or_default(),
+            self.outline * scale,
+            self.shadow * scale,
+            if self.bold { 1 } else { 0 },
+            alignment,
+            self.margin_l as f64 * scale_x,
+            self.margin_r as f64 * scale_x,
+            self.margin_v as f64 * scale,
+        )
+    }
+
+    /// Script resolution for ASS files, in style pixels.
+    pub fn play_res(&self) -> (u32, u32) {
+        (self.play_res_x.round() as u32, STYLE_PLAY_RES_Y as u32)
+    }
+
+    /// A `[V4+ Styles]` section defining this style as `Default`.
+    pub fn ass_styles(&self) -> String {
+        let primary = ass_colour(&self.primary_colour).unwrap_or_default();
+        format!(
+            "[V4+ Styles]
+Format: Name, Fontname, Fontsize, PrimaryColour, SecondaryColour, OutlineColour, BackColour, Bold, Italic, Underline, StrikeOut, ScaleX, ScaleY, Spacing, Angle, BorderStyle, Outline, Shadow, Alignment, MarginL, MarginR, MarginV, Encoding
+Style: Default,{},{:.0},{primary},{primary},{},&H00000000&,{},0,0,0,100,100,0,0,1,{:.1},{:.1},{},{},{},{},1
+",
+            self.font,
+            self.size,
+            ass_colour(&self.outline_colour).unwrap_or_default(),
+            if self.bold { -1 } else { 0 },
+            self.outline,
+            self.shadow,
+            self.position,
+            self.margin_l,
+            self.margin_r,
+            self.margin_v,
+        )
+    }
+}
+
+/// Collects the subtitle style options given on the command line.
+fn cli_style(args: &Args) -> SubtitleStyle {
+    SubtitleStyle {
+        font: args.subtitle_font.clone(),
+        font_file: args.subtitle_font_file.clone(),
+        size: args.subtitle_size,
+        primary_colour: args.subtitle_colour.clone(),
+        outline_colour: args.subtitle_outline_colour.clone(),
+        outline: args.subtitle_outline,
+        shadow: args.subtitle_shadow,
+        bold: None,
+        position: args.subtitle_position,
+        margin_l: args.subtitle_margin_h,
+        margin_r: args.subtitle_margin_h,
+        margin_v: args.subtitle_margin_v,
+        uppercase: args.subtitle_uppercase.then_some(true),
+        max_lines: args.subtitle_max_lines,
+    }
+}
+
+/// Resolves the subtitle style from presets, the configuration file and the command line.
+///
+/// # Arguments
+/// * `args` - Parsed command-line arguments
+/// * `config` - Subtitle section of the configuration file
+/// * `default_preset` - Preset used when neither the command line nor the config names one
+/// * `output` - Output profile whose frame the subtitles are drawn on
+///
+/// # Returns
+/// * `Ok(ResolvedStyle)` - The validated style
+/// * `Err` - If the preset is unknown or a value is invalid
+pub fn resolve_style(
+    args: &Args,
+    config: &SubtitleConfig,
+    default_preset: &str,
+    output: &ResolvedOutput,
+) -> anyhow::Result<ResolvedStyle> {
+    let name = args
+        .subtitle_preset
+        .as_deref()
+        .or(config.preset.as_deref())
+        .unwrap_or(default_preset);
+    // Config presets are layered over the built-in preset of the same name, or
+    // over the default preset for new names.
+    let base = match builtin_preset(name) {
+        Some(preset) => preset,
+        None if config.presets.contains_key(name) => {
+            builtin_preset(default_preset).unwrap_or_default()
+        }
+        None => anyhow::bail!(
+            "Unknown subtitle preset: {name} (built in: {})",
+            PRESETS.join(", ")
+        ),
+    };
+    let mut style = base;
+    if let Some(preset) = config.presets.get(name) {
+        style = style.overridden_by(preset);
+    }
+    let style = style
+        .overridden_by(&config.style)
+        .overridden_by(&cli_style(args));
+
+    let colour = |value: Option<String>, what: &str| -> anyhow::Result<String> {
+        let value = value.unwrap_or_default();
+        anyhow::ensure!(
+            ass_colour(&value).is_some(),
+            "Subtitle {what} must be #RRGGBB, got {value:?}"
+        );
+        Ok(value)
+    };
+    let primary_colour = colour(style.primary_colour, "colour")?;
+    let outline_colour = colour(style.outline_colour, "outline colour")?;
+    let size = style.size.unwrap_or_default();
+    anyhow::ensure!(size > 0.0, "Subtitle size must be positive, got {size}");
+    let outline = style.outline.unwrap_or_default();
+    let shadow = style.shadow.unwrap_or_default();
+    anyhow::ensure!(
+        outline >= 0.0 && shadow >= 0.0,
+        "Subtitle outline and shadow must not be negative"
+    );
+    let position = style.position.unwrap_or(5);
+    anyhow::ensure!(
+        (1..=9).contains(&position),
+        "Subtitle position must be a numpad digit from 1 to 9, got {position}"
+    );
+    let max_lines = style.max_lines.unwrap_or(2);
+    anyhow::ensure!(max_lines > 0, "Subtitle max lines must be at least 1");
+    let play_res_x = STYLE_PLAY_RES_Y * output.width as f64 / output.height as f64;
+    anyhow::ensure!(
+        ((style.margin_l.unwrap_or_default() + style.margin_r.unwrap_or_default()) as f64)
+            < play_res_x,
+        "Subtitle margins leave no room for text"
+    );
+    let fonts_dir = match &style.font_file {
+        Some(file) => {
+            let path = Path::new(file);
+            anyhow::ensure!(path.is_file(), "Subtitle font file not found: {file}");
+            Some(
+                path.parent()
+                    .map(|p| p.to_string_lossy().into_owned())
+                    .filter(|p| !p.is_empty())
+                    .unwrap_or_else(|| ".".to_string()),
+            )
+        }
+        None => None,
+    };
+    // Without a family name, assume the font file is named after its family.
+    let font = style
+        .font
+        .or_else(|| {
+            style
+                .font_file
+                .as_deref()
+                .and_then(|f| Path::new(f).file_stem())
+                .map(|s| s.to_string_lossy().into_owned())
+        })
+        .unwrap_or_else(|| "Arial".to_string());
+    anyhow::ensure!(
+        !font.contains([',', '\'', ':']),
+        "Subtitle font name must not contain commas, quotes or colons: {font}"
+    );
+
+    Ok(ResolvedStyle {
+        font,
+        fonts_dir,
+        size,
+        primary_colour,
+        outline_colour,
+        outline,
+        shadow,
+        bold: style.bold.unwrap_or(false),
+        position,
+        margin_l: style.margin_l.unwrap_or_default(),
+        margin_r: style.margin_r.unwrap_or_default(),
+        margin_v: style.margin_v.unwrap_or_default(),
+        uppercase: style.uppercase.unwrap_or(false),
+        max_lines,
+        play_res_x,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    /// Resolves a built-in preset for a 1080x1920 frame with no other overrides.
+    fn preset_style(name: &str) -> ResolvedStyle {
+        let args = Args::parse_from(["reddit-story", "--subtitle-preset", name]);
+        let output = ResolvedOutput {
+            name: "short".to_string(),
+            width: 1080,
+            height: 1920,
+            fps: 30,
+            crf: 23,
+            x264_preset: "medium".to_string(),
+            audio_bitrate: "192k".to_string(),
+            pix_fmt: "yuv420p".to_string(),
+        };
+        resolve_style(&args, &SubtitleConfig::default(), "classic", &output).unwrap()
+    }
+
+    #[test]
+    fn classic_preset_is_centred_in_ssa_numbering() {
+        let style = preset_style("classic");
+        assert!(
+            style.force_style().contains("Alignment=10"),
+            "{}",
+            style.force_style()
+        );
+    }
+
+    #[test]
+    fn force_style_converts_every_numpad_position() {
+        let mut style = preset_style("classic");
+        let ssa: Vec<u8> = (1..=9)
+            .map(|position| {
+                style.position = position;
+                let force_style = style.force_style();
+                let alignment = force_style.split("Alignment=").nth(1).unwrap();
+                alignment.split(',').next().unwrap().parse().unwrap()
+            })
+            .collect();
+        assert_eq!(ssa, [1, 2, 3, 9, 10, 11, 5, 6, 7]);
+    }
+
+    #[test]
+    fn ass_styles_keep_numpad_alignment() {
+        let style = preset_style("classic");
+        let line = style.ass_styles().lines().last().unwrap().to_string();
+        let fields: Vec<&str> = line.split(',').collect();
+        assert_eq!(fields[fields.len() - 5], "5", "{line}");
+    }
+}
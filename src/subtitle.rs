@@ -1,124 +1,761 @@
 //! Subtitle generation and formatting utilities.
 //!
 //! This module handles the creation of SRT subtitle files with precise timing
-//! based on TTS audio chunks and text analysis.
+//! based on TTS audio chunks and text analysis, and reads SRT and WebVTT files
+//! back into a track for editing and re-timing.
 
+use crate::align::{Alignment, Boundary, align_chunk, heuristic_timings, word_boundaries};
+use crate::audio::SilenceOptions;
+use crate::emphasis::Emphasis;
+use crate::tts::{SynthesizedChunk, WordTiming};
 use regex::Regex;
-use std::fs::File;
+use serde::Serialize;
+use std::fs::{self, File};
 use std::io::Write;
+use std::sync::LazyLock;
+use tracing::debug;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
 
-/// Builds SRT subtitle entries with precise timing from TTS audio chunks.
+static FONT_TAG_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"(?i)<font\s+color="?(#[0-9a-f]{6})"?\s*>"#).unwrap());
+static VOICE_TAG_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^<v(?:\.[^ >]*)?\s+([^>]+)>").unwrap());
+static TAG_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"<[^>]*>").unwrap());
+
+/// Subtitle file format burned into the video.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum SubtitleFormat {
+    /// SubRip, one word at a time
+    Srt,
+    /// Advanced SubStation Alpha with karaoke word highlighting
+    Ass,
+}
+
+/// Cues shorter than this many seconds are dropped.
+const MIN_CUE_SECONDS: f64 = 0.001;
+/// Cues shorter than this many seconds are merged into a neighbouring cue when grouping.
+const TINY_CUE_SECONDS: f64 = 0.12;
+/// A silence longer than this many seconds always separates caption groups.
+const MAX_GROUP_GAP: f64 = 0.6;
+/// Line width, in columns, of subtitle files that are not burned into the video.
+pub const SIDECAR_LINE_WIDTH: usize = 80;
+
+/// How words are grouped into captions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum CaptionGrouping {
+    /// One word per caption
+    Word,
+    /// Up to a fixed number of words per caption
+    Words,
+    /// Lines up to a maximum width that break at punctuation
+    Phrase,
+}
+
+/// Settings for [`Track::grouped`].
+#[derive(Debug, Clone, Copy)]
+pub struct GroupingOptions {
+    /// Grouping mode
+    pub mode: CaptionGrouping,
+    /// Most words per caption in [`CaptionGrouping::Words`] mode
+    pub max_words: usize,
+    /// Most characters per caption in [`CaptionGrouping::Phrase`] mode
+    pub max_chars: usize,
+    /// Shortest time a caption stays on screen, in seconds, if the next one allows it
+    pub min_duration: f64,
+}
+
+/// How caption text is broken into lines.
+#[derive(Debug, Clone, Copy)]
+pub struct LineLayout {
+    /// Widest line, in display columns
+    pub width: usize,
+    /// Most lines per caption
+    pub max_lines: usize,
+}
+
+/// Presentation of a cue beyond its text.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CueStyle {
+    /// Text colour as `#RRGGBB`, or `None` for the default style
+    pub colour: Option<String>,
+}
+
+/// A timed word within a cue.
+#[derive(Debug, Clone)]
+pub struct CueWord {
+    /// The word as displayed
+    pub text: String,
+    /// Start time in seconds
+    pub start: f64,
+    /// End time in seconds
+    pub end: f64,
+    /// Index of the sentence the word belongs to, counted across the whole track
+    pub sentence: usize,
+    /// Punctuation following the word in the story text
+    pub boundary: Option<Boundary>,
+}
+
+/// A single subtitle cue.
+#[derive(Debug, Clone)]
+pub struct Cue {
+    /// Start time in seconds
+    pub start: f64,
+    /// End time in seconds
+    pub end: f64,
+    /// Text shown while the cue is active
+    pub text: String,
+    /// Timed words making up the cue, in absolute seconds
+    pub words: Vec<CueWord>,
+    /// Voice profile that speaks the cue, or `None` for the narrator
+    pub speaker: Option<String>,
+    /// How the cue is drawn
+    pub style: CueStyle,
+    /// Index of the narration chunk the cue belongs to
+    pub chunk: usize,
+}
+
+/// An ordered list of subtitle cues.
+///
+/// Pauses in the narration are gaps between cues rather than cues of their own.
+#[derive(Debug, Clone, Default)]
+pub struct Track {
+    /// Cues in playback order
+    pub cues: Vec<Cue>,
+}
+
+impl Track {
+    /// Checks that cues are ordered, do not overlap and all have a positive length.
+    ///
+    /// # Returns
+    /// * `Ok(())` - If the track is valid
+    /// * `Err` - Describing the first offending cue
+    pub fn validate(&self) -> anyhow::Result<()> {
+        let mut previous_end = 0.0;
+        for (i, cue) in self.cues.iter().enumerate() {
+            let n = i + 1;
+            anyhow::ensure!(
+                cue.start.is_finite() && cue.end.is_finite() && cue.start >= 0.0,
+                "Cue {} (chunk {}) has invalid times {}..{}",
+                n,
+                cue.chunk,
+                cue.start,
+                cue.end
+            );
+            anyhow::ensure!(
+                cue.end > cue.start,
+                "Cue {} (chunk {}, {:?}) has no duration",
+                n,
+                cue.chunk,
+                cue.text
+            );
+            anyhow::ensure!(
+                cue.start >= previous_end,
+                "Cue {} (chunk {}, {:?}) starts at {:.3}s before the previous cue ends at {:.3}s",
+                n,
+                cue.chunk,
+                cue.text,
+                cue.start,
+                previous_end
+            );
+            anyhow::ensure!(
+                cue.words
+                    .iter()
+                    .all(|w| w.start >= cue.start && w.end <= cue.end && w.start <= w.end),
+                "Cue {} (chunk {}, {:?}) has words outside its time span",
+                n,
+                cue.chunk,
+                cue.text
+            );
+            previous_end = cue.end;
+        }
+        Ok(())
+    }
+}
+
+impl Cue {
+    /// Whether `other` may share a caption with this cue.
+    fn compatible(&self, other: &Cue) -> bool {
+        self.speaker == other.speaker
+            && self.style == other.style
+            && self.chunk == other.chunk
+            && other.start - self.end <= MAX_GROUP_GAP
+    }
+
+    /// Appends the words of `other` to this cue.
+    fn append(&mut self, other: Cue) {
+        self.text.push(' ');
+        self.text.push_str(&other.text);
+        self.end = other.end;
+        self.words.extend(other.words);
+    }
+
+    /// Whether the cue's last word is followed by punctuation.
+    fn ends_phrase(&self) -> bool {
+        self.words.last().is_some_and(|w| w.boundary.is_some())
+    }
+}
+
+impl Track {
+    /// Returns a copy of the track with all caption text in upper case.
+    pub fn uppercased(&self) -> Track {
+        let mut track = self.clone();
+        for cue in &mut track.cues {
+            cue.text = cue.text.to_uppercase();
+            for word in &mut cue.words {
+                word.text = word.text.to_uppercase();
+            }
+        }
+        track
+    }
+
+    /// Returns a copy of the track with every time scaled and then shifted.
+    ///
+    /// Cues that end up entirely before zero are dropped and cues that
+    /// straddle it are clipped.
+    ///
+    /// # Arguments
+    /// * `shift` - Seconds added to every time after scaling
+    /// * `scale` - Factor every time is multiplied by
+    ///
+    /// # Returns
+    /// * `Track` - The re-timed track
+    pub fn retimed(&self, shift: f64, scale: f64) -> Track {
+        let time = |t: f64| (t * scale + shift).max(0.0);
+        let cues = self
+            .cues
+            .iter()
+            .filter(|cue| time(cue.end) > 0.0)
+            .map(|cue| Cue {
+                start: time(cue.start),
+                end: time(cue.end),
+                words: cue
+                    .words
+                    .iter()
+                    .map(|w| CueWord {
+                        start: time(w.start),
+                        end: time(w.end),
+                        ..w.clone()
+                    })
+                    .collect(),
+                ..cue.clone()
+            })
+            .collect();
+        Track { cues }
+    }
+
+    /// Splits captions that would wrap onto more lines than the layout allows.
+    ///
+    /// Each part keeps the timing of its words. Cues whose text does not
+    /// match their words word for word (such as imported cues with
+    /// punctuation) are left as they are.
+    ///
+    /// # Arguments
+    /// * `layout` - Line width and most lines per caption
+    ///
+    /// # Returns
+    /// * `Track` - The track with long captions split
+    pub fn fitted(&self, layout: &LineLayout) -> Track {
+        let fits = |text: &str| wrap_text(text, layout.width).len() <= layout.max_lines.max(1);
+        let mut cues = Vec::with_capacity(self.cues.len());
+        for cue in &self.cues {
+            let matches_words = cue.words.len() > 1
+                && cue
+                    .text
+                    .split_whitespace()
+                    .eq(cue.words.iter().map(|w| w.text.as_str()));
+            if fits(&cue.text) || !matches_words {
+                cues.push(cue.clone());
+                continue;
+            }
+            let mut parts: Vec<Vec<CueWord>> = vec![Vec::new()];
+            for word in &cue.words {
+                let current = parts.last_mut().unwrap();
+                let text = current
+                    .iter()
+                    .chain([word])
+                    .map(|w| w.text.as_str())
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                if !current.is_empty() && !fits(&text) {
+                    parts.push(Vec::new());
+                }
+                parts.last_mut().unwrap().push(word.clone());
+            }
+            debug!("Split caption {:?} into {} parts", cue.text, parts.len());
+            for (i, words) in parts.iter().enumerate() {
+                cues.push(Cue {
+                    start: if i == 0 { cue.start } else { words[0].start },
+                    end: parts.get(i + 1).map_or(cue.end, |next| next[0].start),
+                    text: words
+                        .iter()
+                        .map(|w| w.text.as_str())
+                        .collect::<Vec<_>>()
+                        .join(" "),
+                    words: words.clone(),
+                    ..cue.clone()
+                });
+            }
+        }
+        Track { cues }
+    }
+
+    /// Groups cues into captions.
+    ///
+    /// Captions never mix speakers, styles or chunks and never span a long
+    /// pause. Tiny cues are merged into a neighbour where possible, and
+    /// captions shorter than the minimum duration stay on screen into the
+    /// following gap.
+    ///
+    /// # Arguments
+    /// * `options` - Grouping mode, limits and minimum duration
+    ///
+    /// # Returns
+    /// * `Track` - The grouped track; the words of every cue are preserved
+    pub fn grouped(&self, options: &GroupingOptions) -> Track {
+        let mut cues: Vec<Cue> = Vec::new();
+        for cue in self.cues.iter().cloned() {
+            if let Some(last) = cues.last_mut()
+                && last.compatible(&cue)
+            {
+                let fits = match options.mode {
+                    CaptionGrouping::Word => false,
+                    CaptionGrouping::Words => {
+                        last.words.len() + cue.words.len() <= options.max_words
+                    }
+                    CaptionGrouping::Phrase => {
+                        !last.ends_phrase()
+                            && last.text.chars().count() + 1 + cue.text.chars().count()
+                                <= options.max_chars
+                    }
+                };
+                if fits {
+                    last.append(cue);
+                    continue;
+                }
+            }
+            cues.push(cue);
+        }
+
+        // Merge tiny cues into the previous caption, or pull the next one into
+        // them, without joining across punctuation.
+        let mut merged: Vec<Cue> = Vec::with_capacity(cues.len());
+        for cue in cues {
+            if let Some(last) = merged.last_mut()
+                && (last.end - last.start < TINY_CUE_SECONDS
+                    || cue.end - cue.start < TINY_CUE_SECONDS)
+                && !last.ends_phrase()
+                && last.compatible(&cue)
+            {
+                last.append(cue);
+                continue;
+            }
+            merged.push(cue);
+        }
+
+        for i in 0..merged.len() {
+            let limit = merged.get(i + 1).map_or(f64::INFINITY, |next| next.start);
+            let cue = &mut merged[i];
+            if cue.end - cue.start < options.min_duration {
+                cue.end = (cue.start + options.min_duration).min(limit).max(cue.end);
+            }
+        }
+        Track { cues: merged }
+    }
+}
+
+/// Builds a subtitle track with word timing from TTS audio chunks.
 ///
-/// This function analyzes the generated TTS audio files and corresponding text
-/// to create properly timed subtitle entries. It accounts for silence periods,
-/// word-level timing, and natural pauses at punctuation marks.
+/// Engine-reported word timings are used when a chunk has them; otherwise
+/// words are aligned to the chunk's audio with the chosen [`Alignment`].
+/// Each word becomes one cue.
 ///
 /// # Arguments
-/// * `tts_results` - Vector of tuples containing (audio_file_path, text_content)
+/// * `tts_results` - Synthesized chunks in narration order
+/// * `gap_seconds` - Silence inserted between consecutive chunks in the combined audio
+/// * `silence` - How silence before, after and within the speech in each chunk is detected
+/// * `alignment` - How words are timed when the engine gives no timings
 ///
 /// # Returns
-/// * `Ok(Vec<(f64, f64, String)>)` - Vector of (start_time, end_time, text) tuples
-/// * `Err` - If audio files cannot be analyzed or timing calculation fails
-pub fn build_srt_entries(
-    tts_results: &[(String, String)],
-) -> anyhow::Result<Vec<(f64, f64, String)>> {
-    let mut srt_entries = Vec::new();
-    let mut cumulative_seconds = 0.0_f64;
-    for (part, chunk_text) in tts_results.iter() {
-        let dur = crate::audio::wav_duration_seconds(part)?;
-        let leading_silence = crate::audio::detect_leading_silence(part, 500, 2000).unwrap_or(0.0);
-        let start_time_of_chunk = cumulative_seconds + leading_silence;
-        let end_time_of_chunk = start_time_of_chunk + (dur - leading_silence);
-        const COMMA_PAUSE: f64 = 0.2;
-        const SENTENCE_END_PAUSE: f64 = 0.4;
-        static WORD_REGEX: std::sync::LazyLock<Regex> =
-            std::sync::LazyLock::new(|| Regex::new(r"(\w[\w'-]*)|([,.!?])").unwrap());
-        let word_regex = &*WORD_REGEX;
-        let elements: Vec<&str> = word_regex
-            .find_iter(chunk_text)
-            .map(|m| m.as_str())
-            .collect();
-        if elements.is_empty() {
-            srt_entries.push((start_time_of_chunk, end_time_of_chunk, chunk_text.clone()));
-            cumulative_seconds = end_time_of_chunk;
+/// * `Ok(Track)` - A validated subtitle track
+/// * `Err` - If audio files cannot be analyzed or the resulting track is invalid
+pub fn build_track(
+    tts_results: &[SynthesizedChunk],
+    gap_seconds: f64,
+    silence: &SilenceOptions,
+    alignment: Alignment,
+) -> anyhow::Result<Track> {
+    let mut track = Track::default();
+    let mut chunk_start = 0.0_f64;
+    let (mut sentence, mut sentence_open, mut in_title) = (0, false, true);
+    for (i, chunk) in tts_results.iter().enumerate() {
+        if i > 0 {
+            chunk_start += gap_seconds.max(0.0);
+        }
+        let dur = crate::audio::wav_duration_seconds(&chunk.path)?;
+        if chunk.text.trim().is_empty() {
+            // Pauses between segments carry no subtitles.
+            chunk_start += dur;
             continue;
         }
-        let mut total_pause_time = 0.0;
-        let mut word_elements = Vec::new();
-        for &element in &elements {
-            match element {
-                "," => total_pause_time += COMMA_PAUSE,
-                "." | "!" | "?" => total_pause_time += SENTENCE_END_PAUSE,
-                _ => word_elements.push(element),
+        // The title rarely ends with punctuation, but the story always starts a new sentence.
+        if chunk.is_title != in_title {
+            in_title = chunk.is_title;
+            if sentence_open {
+                sentence += 1;
+                sentence_open = false;
             }
         }
-        let word_time_available = (dur - leading_silence - total_pause_time).max(0.0);
-        let alpha = 0.75;
-        let total_weight: f64 = word_elements
-            .iter()
-            .map(|w| (w.chars().count() as f64).powf(alpha))
-            .sum();
-        let mut current_time_in_chunk = start_time_of_chunk;
-        for element in elements {
-            match element {
-                "," => {
-                    let pause_start = current_time_in_chunk;
-                    let pause_end = pause_start + COMMA_PAUSE;
-                    srt_entries.push((pause_start, pause_end, String::from(" ")));
-                    current_time_in_chunk = pause_end;
-                }
-                "." | "!" | "?" | ";" => {
-                    let pause_start = current_time_in_chunk;
-                    let pause_end = pause_start + SENTENCE_END_PAUSE;
-                    srt_entries.push((pause_start, pause_end, String::from(" ")));
-                    current_time_in_chunk = pause_end;
-                }
-                word => {
-                    let word_weight = (word.chars().count() as f64).powf(alpha);
-                    let word_duration = if total_weight > 0.0 {
-                        word_time_available * word_weight / total_weight
-                    } else {
-                        0.0
-                    };
-                    let word_start = current_time_in_chunk;
-                    let word_end = word_start + word_duration;
-                    srt_entries.push((word_start, word_end, word.to_string()));
-                    current_time_in_chunk = word_end;
-                }
+        let timings = match &chunk.timings {
+            Some(timings) => timings.clone(),
+            None => align_chunk(&chunk.path, &chunk.text, alignment, silence)?,
+        };
+        let timings = if timings.is_empty() {
+            vec![WordTiming {
+                word: chunk.text.trim().to_string(),
+                start: 0.0,
+                end: dur,
+            }]
+        } else {
+            timings
+        };
+        let boundaries = word_boundaries(&chunk.text);
+        let count = timings.len();
+        for (k, t) in timings.into_iter().enumerate() {
+            // Engine timings may split words differently from the text, so
+            // each timing covers its proportional share of the text's words.
+            let words = k * boundaries.len() / count..(k + 1) * boundaries.len() / count;
+            let boundary = boundaries[words].iter().flatten().max().copied();
+            let word_sentence = sentence;
+            sentence_open = true;
+            if boundary == Some(Boundary::SentenceEnd) {
+                sentence += 1;
+                sentence_open = false;
             }
+
+            let previous_end = track.cues.last().map_or(0.0, |c| c.end);
+            let start = (chunk_start + t.start).max(previous_end);
+            let end = chunk_start + t.end;
+            if end - start < MIN_CUE_SECONDS {
+                debug!("Dropping zero-length subtitle word {:?}", t.word);
+                continue;
+            }
+            track.cues.push(Cue {
+                start,
+                end,
+                text: t.word.clone(),
+                words: vec![CueWord {
+                    text: t.word,
+                    start,
+                    end,
+                    sentence: word_sentence,
+                    boundary,
+                }],
+                speaker: chunk.voice.clone(),
+                style: CueStyle {
+                    colour: chunk.colour.clone(),
+                },
+                chunk: i,
+            });
         }
-        cumulative_seconds = end_time_of_chunk;
+        chunk_start += dur;
+    }
+    track.validate()?;
+    Ok(track)
+}
+
+/// Wraps subtitle text in an SRT font colour tag when a voice colour is set.
+///
+/// # Arguments
+/// * `text` - Subtitle text
+/// * `colour` - Colour as `#RRGGBB`, or `None` for the default style
+///
+/// # Returns
+/// * `String` - The text, tagged if a colour was given
+fn colourize(text: &str, colour: Option<&str>) -> String {
+    match colour {
+        Some(colour) => format!("<font color=\"{colour}\">{text}</font>"),
+        None => text.to_string(),
     }
-    Ok(srt_entries)
 }
 
-/// Writes subtitle entries to an SRT format file.
+/// Writes a subtitle track to an SRT format file.
 ///
 /// # Arguments
 /// * `path` - Output path for the SRT file
-/// * `entries` - Vector of (start_time, end_time, text) tuples
+/// * `track` - Subtitle track to write
+/// * `width` - Widest line, in display columns
+/// * `emphasis` - Keywords to colour and follow with emoji
 ///
 /// # Returns
 /// * `Ok(())` - If the file was successfully written
 /// * `Err` - If the file cannot be created or written to
-pub fn write_srt(path: &str, entries: &[(f64, f64, String)]) -> anyhow::Result<()> {
+pub fn write_srt(
+    path: &str,
+    track: &Track,
+    width: usize,
+    emphasis: &Emphasis,
+) -> anyhow::Result<()> {
     let mut f = File::create(path)?;
-    for (i, (start, end, text)) in entries.iter().enumerate() {
+    for (i, cue) in track.cues.iter().enumerate() {
         writeln!(f, "{}", i + 1)?;
         writeln!(
             f,
             "{} --> {}",
-            format_srt_time(*start),
-            format_srt_time(*end)
+            format_srt_time(cue.start),
+            format_srt_time(cue.end)
         )?;
-        for line in wrap_text(text, 80) {
-            writeln!(f, "{line}")?;
+        let text = wrap_text(&emphasis.with_emoji(&cue.text), width)
+            .iter()
+            .map(|line| {
+                line.split(' ')
+                    .map(|word| {
+                        let colour = emphasis.lookup(word).and_then(|s| s.colour.as_deref());
+                        colourize(word, colour)
+                    })
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        writeln!(f, "{}", colourize(&text, cue.style.colour.as_deref()))?;
+        writeln!(f)?;
+    }
+    Ok(())
+}
+
+/// Writes a subtitle track to a WebVTT file.
+///
+/// Cues spoken by a dialogue or title voice are tagged with the voice name.
+///
+/// # Arguments
+/// * `path` - Output path for the VTT file
+/// * `track` - Subtitle track to write
+///
+/// # Returns
+/// * `Ok(())` - If the file was successfully written
+/// * `Err` - If the file cannot be created or written to
+pub fn write_vtt(path: &str, track: &Track) -> anyhow::Result<()> {
+    let mut f = File::create(path)?;
+    writeln!(f, "WEBVTT")?;
+    writeln!(f)?;
+    for cue in &track.cues {
+        writeln!(
+            f,
+            "{} --> {}",
+            format_vtt_time(cue.start),
+            format_vtt_time(cue.end)
+        )?;
+        for (i, line) in wrap_text(&cue.text, SIDECAR_LINE_WIDTH)
+            .into_iter()
+            .enumerate()
+        {
+            let line = line
+                .replace('&', "&amp;")
+                .replace('<', "&lt;")
+                .replace('>', "&gt;");
+            match &cue.speaker {
+                Some(speaker) if i == 0 => writeln!(f, "<v {speaker}>{line}")?,
+                _ => writeln!(f, "{line}")?,
+            }
         }
         writeln!(f)?;
     }
     Ok(())
 }
 
+/// One word of a JSON transcript.
+#[derive(Serialize)]
+struct TranscriptWord<'a> {
+    word: &'a str,
+    start: f64,
+    end: f64,
+    chunk: usize,
+    sentence: usize,
+    speaker: Option<&'a str>,
+}
+
+/// JSON transcript with word timings.
+#[derive(Serialize)]
+struct Transcript<'a> {
+    words: Vec<TranscriptWord<'a>>,
+}
+
+/// Writes the words of a subtitle track with their timings as JSON.
+///
+/// # Arguments
+/// * `path` - Output path for the JSON file
+/// * `track` - Subtitle track to write
+///
+/// # Returns
+/// * `Ok(())` - If the file was successfully written
+/// * `Err` - If serialization or writing fails
+pub fn write_transcript(path: &str, track: &Track) -> anyhow::Result<()> {
+    let millis = |t: f64| (t * 1000.0).round() / 1000.0;
+    let words = track
+        .cues
+        .iter()
+        .flat_map(|cue| {
+            cue.words.iter().map(|w| TranscriptWord {
+                word: &w.text,
+                start: millis(w.start),
+                end: millis(w.end),
+                chunk: cue.chunk,
+                sentence: w.sentence,
+                speaker: cue.speaker.as_deref(),
+            })
+        })
+        .collect();
+    fs::write(path, serde_json::to_string_pretty(&Transcript { words })?)?;
+    Ok(())
+}
+
+/// Parses an SRT or WebVTT timestamp (`HH:MM:SS,mmm`, `HH:MM:SS.mmm` or `MM:SS.mmm`).
+fn parse_timestamp(s: &str) -> Option<f64> {
+    let (clock, millis) = s.trim().rsplit_once([',', '.'])?;
+    if millis.len() != 3 || !millis.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let mut seconds = 0.0;
+    let parts: Vec<&str> = clock.split(':').collect();
+    if !(2..=3).contains(&parts.len()) {
+        return None;
+    }
+    for part in parts {
+        seconds = seconds * 60.0 + part.parse::<u32>().ok()? as f64;
+    }
+    Some(seconds + millis.parse::<u32>().ok()? as f64 / 1000.0)
+}
+
+/// Turns the text lines of a cue into plain text, speaker and colour.
+fn parse_cue_text(lines: &[&str]) -> (String, Option<String>, Option<String>) {
+    let text = lines.join(" ");
+    let speaker = VOICE_TAG_REGEX
+        .captures(&text)
+        .map(|c| c[1].trim().to_string());
+    let colour = FONT_TAG_REGEX.captures(&text).map(|c| c[1].to_uppercase());
+    let text = TAG_REGEX
+        .replace_all(&text, "")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&nbsp;", " ")
+        .replace("&amp;", "&");
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    (text, speaker, colour)
+}
+
+/// Splits subtitle file contents into blocks separated by blank lines.
+///
+/// Lines holding only whitespace count as blank, and any number of them may
+/// separate two blocks.
+///
+/// # Arguments
+/// * `content` - File contents with `\n` line endings
+///
+/// # Returns
+/// * `Vec<(usize, Vec<&str>)>` - Each block's 1-based first line number and its lines
+fn blocks(content: &str) -> Vec<(usize, Vec<&str>)> {
+    let mut blocks: Vec<(usize, Vec<&str>)> = Vec::new();
+    let mut in_block = false;
+    for (n, line) in content.lines().enumerate() {
+        if line.trim().is_empty() {
+            in_block = false;
+            continue;
+        }
+        match blocks.last_mut() {
+            Some((_, lines)) if in_block => lines.push(line),
+            _ => blocks.push((n + 1, vec![line])),
+        }
+        in_block = true;
+    }
+    blocks
+}
+
+/// Reads an SRT or WebVTT file into a subtitle track.
+///
+/// The format is detected from the `WEBVTT` header. Speaker tags (`<v name>`)
+/// and font colours written by [`write_vtt`] and [`write_srt`] are restored;
+/// other markup is dropped. Files do not store word timings, so each cue's
+/// words are spread over it by length, and every cue becomes its own chunk.
+///
+/// # Arguments
+/// * `path` - Path of the SRT or VTT file
+///
+/// # Returns
+/// * `Ok(Track)` - The cues in file order; the track is not validated
+/// * `Err` - If the file cannot be read or a timing line is malformed
+pub fn parse_subtitles(path: &str) -> anyhow::Result<Track> {
+    let content = fs::read_to_string(path)?
+        .replace("\r\n", "\n")
+        .replace('\r', "\n");
+    let content = content.trim_start_matches('\u{feff}');
+    let vtt = content.trim_start().starts_with("WEBVTT");
+
+    let mut track = Track::default();
+    let mut sentence = 0;
+    for (first_line, lines) in blocks(content) {
+        let Some(timing) = lines.iter().position(|l| l.contains("-->")) else {
+            // WebVTT headers, comments, styles and regions carry no cues.
+            anyhow::ensure!(vtt, "{path}:{first_line}: expected a cue timing line");
+            continue;
+        };
+        let (start, end) = lines[timing].split_once("-->").unwrap();
+        // WebVTT cue settings follow the end time.
+        let end = end.split_whitespace().next().unwrap_or_default();
+        let (Some(start), Some(end)) = (parse_timestamp(start), parse_timestamp(end)) else {
+            anyhow::bail!(
+                "{path}:{}: invalid cue timing {:?}",
+                first_line + timing,
+                lines[timing]
+            );
+        };
+
+        let (text, speaker, colour) = parse_cue_text(&lines[timing + 1..]);
+        let timings = heuristic_timings(&text, start, end);
+        let boundaries = word_boundaries(&text);
+        let mut words: Vec<CueWord> = timings
+            .into_iter()
+            .zip(boundaries)
+            .map(|(t, boundary)| {
+                let word = CueWord {
+                    text: t.word,
+                    start: t.start,
+                    end: t.end,
+                    sentence,
+                    boundary,
+                };
+                if boundary == Some(Boundary::SentenceEnd) {
+                    sentence += 1;
+                }
+                word
+            })
+            .collect();
+        if words.is_empty() && !text.is_empty() {
+            words.push(CueWord {
+                text: text.clone(),
+                start,
+                end,
+                sentence,
+                boundary: None,
+            });
+        }
+        track.cues.push(Cue {
+            start,
+            end,
+            text,
+            words,
+            speaker,
+            style: CueStyle { colour },
+            chunk: track.cues.len(),
+        });
+    }
+    Ok(track)
+}
+
+/// Formats a time value in seconds to WebVTT timestamp format (HH:MM:SS.mmm).
+fn format_vtt_time(seconds: f64) -> String {
+    format_srt_time(seconds).replace(',', ".")
+}
+
 /// Formats a time value in seconds to SRT timestamp format (HH:MM:SS,mmm).
 ///
 /// # Arguments
@@ -137,31 +774,219 @@ fn format_srt_time(seconds: f64) -> String {
     format!("{h:02}:{m:02}:{s:02},{ms:03}")
 }
 
-/// Wraps text to fit within a specified character width for subtitle display.
+/// Splits a word wider than `width` columns into pieces that fit.
+fn break_word(word: &str, width: usize) -> Vec<String> {
+    let mut pieces = vec![String::new()];
+    let mut current_width = 0;
+    for c in word.chars() {
+        let w = c.width().unwrap_or(0);
+        if current_width + w > width && current_width > 0 {
+            pieces.push(String::new());
+            current_width = 0;
+        }
+        pieces.last_mut().unwrap().push(c);
+        current_width += w;
+    }
+    pieces
+}
+
+/// Fills lines greedily with words no wider than `width` columns each.
+fn fill_lines(words: &[String], width: usize) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    let mut current_width = 0;
+    for word in words {
+        let w = word.width();
+        match lines.last_mut() {
+            Some(line) if current_width + 1 + w <= width => {
+                line.push(' ');
+                line.push_str(word);
+                current_width += 1 + w;
+            }
+            _ => {
+                lines.push(word.clone());
+                current_width = w;
+            }
+        }
+    }
+    lines
+}
+
+/// Wraps text to fit within a display width for subtitle display.
+///
+/// Widths are measured in terminal-style display columns, so wide glyphs
+/// such as CJK characters and most emoji count twice. Words wider than a
+/// line are broken, and lines are balanced: the text is wrapped to the
+/// narrowest width that still needs no more lines than `width` does.
 ///
 /// # Arguments
 /// * `s` - Text to wrap
-/// * `width` - Maximum characters per line
+/// * `width` - Widest line, in display columns
 ///
 /// # Returns
 /// * `Vec<String>` - Vector of wrapped text lines
-fn wrap_text(s: &str, width: usize) -> Vec<String> {
-    let mut lines = Vec::new();
-    let mut current = String::new();
-    for word in s.split_whitespace() {
-        if current.len() + word.len() + 1 > width && !current.is_empty() {
-            lines.push(current.clone());
-            current.clear();
-            current.push_str(word);
+pub fn wrap_text(s: &str, width: usize) -> Vec<String> {
+    let width = width.max(1);
+    let words: Vec<String> = s
+        .split_whitespace()
+        .flat_map(|word| break_word(word, width))
+        .collect();
+    let lines = fill_lines(&words, width);
+    if lines.len() < 2 {
+        return lines;
+    }
+    // The line count only grows as the width shrinks, so search for the
+    // narrowest width that keeps it.
+    let (mut lo, mut hi) = (words.iter().map(|w| w.width()).max().unwrap_or(1), width);
+    while lo < hi {
+        let mid = (lo + hi) / 2;
+        if fill_lines(&words, mid).len() <= lines.len() {
+            hi = mid;
         } else {
-            if !current.is_empty() {
-                current.push(' ');
-            }
-            current.push_str(word);
+            lo = mid + 1;
         }
     }
-    if !current.is_empty() {
-        lines.push(current);
+    fill_lines(&words, hi)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Writes `content` to a temporary file and parses it.
+    fn parse(name: &str, content: &str) -> anyhow::Result<Track> {
+        let path = std::env::temp_dir().join(format!("subtitle_{name}_{}.srt", std::process::id()));
+        fs::write(&path, content).unwrap();
+        let track = parse_subtitles(path.to_str().unwrap());
+        fs::remove_file(&path).unwrap();
+        track
+    }
+
+    #[test]
+    fn crlf_and_extra_blank_lines_are_accepted() {
+        let track = parse(
+            "crlf",
+            "1\r\n00:00:00,000 --> 00:00:01,000\r\nHello there\r\n\r\n \r\n\r\n\
+             2\r\n00:00:01,500 --> 00:00:02,000\r\nBye\r\n",
+        )
+        .unwrap();
+        let cues: Vec<(&str, f64, f64)> = track
+            .cues
+            .iter()
+            .map(|c| (c.text.as_str(), c.start, c.end))
+            .collect();
+        assert_eq!(cues, [("Hello there", 0.0, 1.0), ("Bye", 1.5, 2.0)]);
+    }
+
+    #[test]
+    fn errors_report_the_line_of_the_bad_timing() {
+        let error = parse(
+            "bad_timing",
+            "1\n00:00:00,000 --> 00:00:01,000\nHello\n\n\n\n2\n00:00:01,500 --> soon\nBye\n",
+        )
+        .unwrap_err()
+        .to_string();
+        assert!(error.contains(":8: invalid cue timing"), "{error}");
+    }
+
+    #[test]
+    fn srt_blocks_without_timing_are_rejected_at_their_first_line() {
+        let error = parse(
+            "no_timing",
+            "1\r\n00:00:00,000 --> 00:00:01,000\r\nHello\r\n\r\n\r\nstray text\r\n",
+        )
+        .unwrap_err()
+        .to_string();
+        assert!(error.contains(":6: expected a cue timing line"), "{error}");
+    }
+
+    /// A one-word cue from `start` to `end`.
+    fn cue(text: &str, start: f64, end: f64) -> Cue {
+        Cue {
+            start,
+            end,
+            text: text.to_string(),
+            words: vec![CueWord {
+                text: text.to_string(),
+                start,
+                end,
+                sentence: 0,
+                boundary: None,
+            }],
+            speaker: None,
+            style: CueStyle::default(),
+            chunk: 0,
+        }
+    }
+
+    /// Validates a track of cues and returns the error message, if any.
+    fn validation_error(cues: Vec<Cue>) -> Option<String> {
+        Track { cues }.validate().err().map(|e| e.to_string())
+    }
+
+    #[test]
+    fn ordered_cues_are_valid() {
+        let cues = vec![
+            cue("one", 0.0, 0.5),
+            cue("two", 0.5, 1.0),
+            cue("three", 1.2, 2.0),
+        ];
+        assert_eq!(validation_error(cues), None);
+    }
+
+    #[test]
+    fn overlapping_cues_are_rejected() {
+        let error = validation_error(vec![cue("one", 0.0, 1.0), cue("two", 0.8, 1.5)]).unwrap();
+        assert!(
+            error.contains("Cue 2") && error.contains("before the previous cue ends"),
+            "{error}"
+        );
+    }
+
+    #[test]
+    fn zero_length_cues_are_rejected() {
+        let error = validation_error(vec![cue("one", 0.0, 1.0), cue("two", 1.5, 1.5)]).unwrap();
+        assert!(
+            error.contains("Cue 2") && error.contains("no duration"),
+            "{error}"
+        );
+    }
+
+    #[test]
+    fn out_of_order_cues_are_rejected() {
+        let error = validation_error(vec![cue("two", 2.0, 3.0), cue("one", 0.0, 1.0)]).unwrap();
+        assert!(
+            error.contains("Cue 2") && error.contains("before the previous cue ends"),
+            "{error}"
+        );
+    }
+
+    #[test]
+    fn wide_glyphs_count_twice() {
+        let lines = wrap_text("你好世界 こんにちは", 8);
+        assert_eq!(lines, ["你好世界", "こんにち", "は"]);
+        let lines = wrap_text("🎉🎉 ok 🎉", 5);
+        assert_eq!(lines, ["🎉🎉", "ok 🎉"]);
+        assert!(lines.iter().all(|l| l.width() <= 5), "{lines:?}");
+    }
+
+    #[test]
+    fn lines_are_balanced() {
+        // Greedy filling would give "the quick brown fox" and "jumps".
+        assert_eq!(
+            wrap_text("the quick brown fox jumps", 20),
+            ["the quick brown", "fox jumps"]
+        );
+        assert_eq!(wrap_text("short line", 20), ["short line"]);
+    }
+
+    #[test]
+    fn words_wider_than_a_line_are_broken() {
+        assert_eq!(
+            wrap_text("supercalifragilistic", 8),
+            ["supercal", "ifragili", "stic"]
+        );
+        let lines = wrap_text("a supercalifragilistic word", 8);
+        assert!(lines.iter().all(|l| l.width() <= 8), "{lines:?}");
+        assert_eq!(lines.concat().replace(' ', ""), "asupercalifragilisticword");
     }
-    lines
 }
@@ -0,0 +1,366 @@
+//! Title card shown while the post title is narrated.
+//!
+//! The card mimics a Reddit post: subreddit icon and name, username, the
+//! title and the vote and comment counts. It is drawn into a PNG with
+//! ffmpeg's `drawtext` from local fonts only, so rendering never touches the
+//! network, and later overlaid on the video for the length of the title
+//! narration. Colours come from a light or dark theme, or one defined in the
+//! configuration file.
+
+use crate::args::Args;
+use crate::ffmpeg::{FfmpegCommand, Filter, FilterChain, Input, Output};
+use crate::reddit::Story;
+use crate::style::ass_colour;
+use crate::subtitle::wrap_text;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::Path;
+use tracing::info;
+
+/// Names of the built-in themes.
+const THEMES: &[&str] = &["light", "dark"];
+/// Width of the card layout grid; sizes below are in these units.
+const GRID_WIDTH: f64 = 1000.0;
+/// Padding around the card contents, in grid units.
+const PADDING: f64 = 48.0;
+/// Size of the subreddit icon, in grid units.
+const ICON_SIZE: f64 = 80.0;
+/// Title font sizes tried in turn until the title fits in `MAX_TITLE_LINES`.
+const TITLE_FONT_SIZES: &[f64] = &[52.0, 44.0, 38.0];
+/// Most title lines before a smaller font size is tried.
+const MAX_TITLE_LINES: usize = 6;
+/// Average width of a bold glyph relative to the font size.
+const AVERAGE_BOLD_GLYPH_WIDTH: f64 = 0.58;
+
+/// Colours of a title card as `#RRGGBB`; unset values come from the theme.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct CardTheme {
+    /// Card background
+    pub background: Option<String>,
+    /// Title and subreddit name
+    pub text: Option<String>,
+    /// Username and vote counts
+    pub secondary: Option<String>,
+    /// Icon background when no icon image is set
+    pub accent: Option<String>,
+}
+
+impl CardTheme {
+    /// Returns this theme with every colour set in `other` replaced.
+    fn overridden_by(self, other: &CardTheme) -> Self {
+        let other = other.clone();
+        Self {
+            background: other.background.or(self.background),
+            text: other.text.or(self.text),
+            secondary: other.secondary.or(self.secondary),
+            accent: other.accent.or(self.accent),
+        }
+    }
+}
+
+/// Title card section of the configuration file.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct TitleCardConfig {
+    /// Whether to show the card; `--title-card` also enables it
+    pub enabled: Option<bool>,
+    /// Name of the theme
+    pub theme: Option<String>,
+    /// Named themes, layered over the built-in theme of the same name
+    pub themes: HashMap<String, CardTheme>,
+    /// Font file for the username and counts
+    pub font: Option<String>,
+    /// Font file for the subreddit name and title [default: `font`]
+    pub bold_font: Option<String>,
+    /// Subreddit icon image, drawn as a square
+    pub icon: Option<String>,
+    /// Card width in percent of the frame width
+    pub width: Option<u32>,
+}
+
+/// Returns a built-in theme with every colour set.
+fn builtin_theme(name: &str) -> Option<CardTheme> {
+    let theme = |background: &str, text: &str, secondary: &str| CardTheme {
+        background: Some(background.to_string()),
+        text: Some(text.to_string()),
+        secondary: Some(secondary.to_string()),
+        accent: Some("#FF4500".to_string()),
+    };
+    match name {
+        "light" => Some(theme("#FFFFFF", "#1A1A1B", "#787C7E")),
+        "dark" => Some(theme("#1A1A1B", "#D7DADC", "#818384")),
+        _ => None,
+    }
+}
+
+/// A fully specified, validated title card template.
+#[derive(Debug, Clone)]
+pub struct TitleCard {
+    /// Name of the theme
+    pub theme: String,
+    background: String,
+    text: String,
+    secondary: String,
+    accent: String,
+    font: Option<String>,
+    bold_font: Option<String>,
+    icon: Option<String>,
+    width_percent: u32,
+}
+
+/// A rendered title card and when it is shown.
+#[derive(Debug, Clone)]
+pub struct CardOverlay {
+    /// PNG image of the card
+    pub path: String,
+    /// When the card appears, in seconds
+    pub start: f64,
+    /// When the card disappears, in seconds
+    pub end: f64,
+}
+
+impl CardOverlay {
+    /// The `overlay` filter placing the card in the upper part of the frame while it is shown.
+    pub fn filter(&self) -> Filter {
+        Filter::new("overlay")
+            .opt("x", "(W-w)/2")
+            .opt("y", "(H-h)/3")
+            .opt(
+                "enable",
+                format!("between(t,{:.3},{:.3})", self.start, self.end),
+            )
+    }
+}
+
+/// Converts `#RRGGBB` into the `0xRRGGBB` form ffmpeg colour options take.
+fn ffmpeg_colour(hex: &str) -> String {
+    format!("0x{}", hex.trim_start_matches('#'))
+}
+
+/// Formats a count the way Reddit shows it, e.g. `12.3k`.
+fn short_count(count: i64) -> String {
+    let magnitude = count.unsigned_abs() as f64;
+    let sign = if count < 0 { "-" } else { "" };
+    if magnitude >= 1_000_000.0 {
+        format!("{sign}{:.1}M", magnitude / 1_000_000.0)
+    } else if magnitude >= 10_000.0 {
+        format!("{sign}{:.1}k", magnitude / 1_000.0)
+    } else {
+        count.to_string()
+    }
+}
+
+/// Resolves the title card template from the configuration file and the command line.
+///
+/// # Arguments
+/// * `args` - Parsed command-line arguments
+/// * `config` - Title card section of the configuration file
+///
+/// # Returns
+/// * `Ok(Some(TitleCard))` - The validated template, if the card is enabled
+/// * `Ok(None)` - If the card is disabled
+/// * `Err` - If the theme is unknown, a colour is invalid or a file is missing
+pub fn resolve_title_card(
+    args: &Args,
+    config: &TitleCardConfig,
+) -> anyhow::Result<Option<TitleCard>> {
+    if !args.title_card && !config.enabled.unwrap_or(false) {
+        return Ok(None);
+    }
+    let name = args
+        .title_card_theme
+        .as_deref()
+        .or(config.theme.as_deref())
+        .unwrap_or("light");
+    let base = match builtin_theme(name) {
+        Some(theme) => theme,
+        None if config.themes.contains_key(name) => builtin_theme("light").unwrap_or_default(),
+        None => anyhow::bail!(
+            "Unknown title card theme: {name} (built in: {})",
+            THEMES.join(", ")
+        ),
+    };
+    let theme = match config.themes.get(name) {
+        Some(configured) => base.overridden_by(configured),
+        None => base,
+    };
+    let colour = |field: &str, value: Option<String>| -> anyhow::Result<String> {
+        let value = value.unwrap_or_default();
+        anyhow::ensure!(
+            ass_colour(&value).is_some(),
+            "Title card {field} colour must be #RRGGBB, got {value:?}"
+        );
+        Ok(ffmpeg_colour(&value))
+    };
+
+    let font = args.title_card_font.clone().or(config.font.clone());
+    let bold_font = config.bold_font.clone().or(font.clone());
+    let icon = args.title_card_icon.clone().or(config.icon.clone());
+    for path in [&font, &bold_font, &icon].into_iter().flatten() {
+        anyhow::ensure!(
+            Path::new(path).exists(),
+            "Title card file not found: {path}"
+        );
+    }
+    let width_percent = config.width.unwrap_or(90);
+    anyhow::ensure!(
+        (10..=100).contains(&width_percent),
+        "Title card width must be between 10 and 100 percent, got {width_percent}"
+    );
+
+    Ok(Some(TitleCard {
+        theme: name.to_string(),
+        background: colour("background", theme.background)?,
+        text: colour("text", theme.text)?,
+        secondary: colour("secondary", theme.secondary)?,
+        accent: colour("accent", theme.accent)?,
+        font,
+        bold_font,
+        icon,
+        width_percent,
+    }))
+}
+
+impl TitleCard {
+    /// A `drawtext` filter for one line of text.
+    fn text(&self, text: &str, bold: bool, size: f64, colour: &str) -> Filter {
+        let filter = Filter::new("drawtext")
+            .opt("text", text)
+            .opt("expansion", "none")
+            .opt("fontsize", size.round())
+            .opt("fontcolor", colour);
+        match if bold { &self.bold_font } else { &self.font } {
+            Some(file) => filter.opt("fontfile", file),
+            None => filter.opt("font", if bold { "Sans:style=Bold" } else { "Sans" }),
+        }
+    }
+
+    /// Renders the card for a story into a PNG sized for the output frame.
+    ///
+    /// # Arguments
+    /// * `story` - Story whose subreddit, author, title and counts are shown
+    /// * `frame_width` - Width of the output video in pixels
+    /// * `path` - Where the PNG is written
+    ///
+    /// # Returns
+    /// * `Ok(())` - If the card was rendered
+    /// * `Err` - If ffmpeg fails
+    pub fn render(&self, story: &Story, frame_width: u32, path: &str) -> anyhow::Result<()> {
+        let width = (frame_width * self.width_percent / 100).max(2) & !1;
+        let unit = width as f64 / GRID_WIDTH;
+        let px = |grid: f64| (grid * unit).round();
+
+        // Shrink the title until it fits, keeping the smallest size otherwise.
+        let columns =
+            |size: f64| ((GRID_WIDTH - 2.0 * PADDING) / (size * AVERAGE_BOLD_GLYPH_WIDTH)) as usize;
+        let (title_size, lines) = TITLE_FONT_SIZES
+            .iter()
+            .map(|&size| (size, wrap_text(&story.title, columns(size))))
+            .find(|(_, lines)| lines.len() <= MAX_TITLE_LINES)
+            .unwrap_or_else(|| {
+                let size = TITLE_FONT_SIZES[TITLE_FONT_SIZES.len() - 1];
+                (size, wrap_text(&story.title, columns(size)))
+            });
+        let line_height = title_size * 1.28;
+        let title_top = PADDING + ICON_SIZE + 32.0;
+        let footer_top = title_top + lines.len() as f64 * line_height + 20.0;
+        let height = (px(footer_top + 30.0 + PADDING) as u32).max(2) & !1;
+
+        let mut filters = Vec::new();
+        let name_left = px(PADDING + ICON_SIZE + 24.0);
+        if self.icon.is_none() {
+            filters.push(
+                Filter::new("drawbox")
+                    .opt("x", px(PADDING))
+                    .opt("y", px(PADDING))
+                    .opt("w", px(ICON_SIZE))
+                    .opt("h", px(ICON_SIZE))
+                    .opt("color", &self.accent)
+                    .opt("t", "fill"),
+            );
+            filters.push(
+                self.text("r/", true, px(40.0), "0xFFFFFF")
+                    .opt("x", format!("{}+({}-tw)/2", px(PADDING), px(ICON_SIZE)))
+                    .opt("y", format!("{}+({}-th)/2", px(PADDING), px(ICON_SIZE))),
+            );
+        }
+        filters.push(
+            self.text(
+                &format!("r/{}", story.subreddit),
+                true,
+                px(36.0),
+                &self.text,
+            )
+            .opt("x", name_left)
+            .opt("y", px(PADDING + 2.0)),
+        );
+        filters.push(
+            self.text(
+                &format!("u/{}", story.author),
+                false,
+                px(30.0),
+                &self.secondary,
+            )
+            .opt("x", name_left)
+            .opt("y", px(PADDING + 46.0)),
+        );
+        for (i, line) in lines.iter().enumerate() {
+            filters.push(
+                self.text(line, true, px(title_size), &self.text)
+                    .opt("x", px(PADDING))
+                    .opt("y", px(title_top + i as f64 * line_height)),
+            );
+        }
+        let counts = format!(
+            "{} upvotes  ·  {} comments",
+            short_count(story.score),
+            short_count(story.num_comments.try_into().unwrap_or(i64::MAX))
+        );
+        filters.push(
+            self.text(&counts, false, px(30.0), &self.secondary)
+                .opt("x", px(PADDING))
+                .opt("y", px(footer_top)),
+        );
+
+        let mut cmd = FfmpegCommand::new(Output::new(path).option("-frames:v", "1"));
+        let canvas = cmd.input(
+            Input::new(format!("color=c={}:s={width}x{height}", self.background))
+                .option("-f", "lavfi"),
+        );
+        let drawn = |output: &str| {
+            filters.iter().cloned().fold(
+                FilterChain::new(&[&format!("{canvas}:v")], output),
+                FilterChain::then,
+            )
+        };
+        match &self.icon {
+            Some(icon) => {
+                let image = cmd.input(Input::new(icon));
+                let size = px(ICON_SIZE);
+                cmd.chain(drawn("base"))
+                    .chain(
+                        FilterChain::new(&[&format!("{image}:v")], "icon")
+                            .then(Filter::new("scale").arg(size).arg(size)),
+                    )
+                    .chain(
+                        FilterChain::new(&["base", "icon"], "card").then(
+                            Filter::new("overlay")
+                                .opt("x", px(PADDING))
+                                .opt("y", px(PADDING)),
+                        ),
+                    );
+            }
+            None => {
+                cmd.chain(drawn("card"));
+            }
+        }
+        cmd.map("[card]").log_level("error");
+        cmd.run("render the title card")?;
+        info!(
+            "Title card ({} theme, {}x{}) written to {}",
+            self.theme, width, height, path
+        );
+        Ok(())
+    }
+}
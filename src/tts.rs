@@ -1,46 +1,960 @@
-//! Text-to-speech generation using Piper TTS engine.
+//! Text-to-speech generation behind a pluggable engine interface.
 //!
-//! This module provides functionality to convert text chunks into audio files
-//! using the Piper TTS system.
+//! The pipeline only talks to the [`TtsEngine`] trait. Concrete backends wrap
+//! the Piper CLI, eSpeak NG, or an arbitrary user-supplied command template, so
+//! voices and engines can be swapped without code changes.
 
+use crate::args::Args;
+use crate::cache::{CachedEngine, TtsCache};
+use crate::config::{TtsConfig, VoiceProfile};
+use crate::piper::{PiperEngine, PiperVoice};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
 use std::io::Write;
-use std::process::{Command, Stdio};
-use tracing::error;
+use std::process::{Command, ExitStatus, Stdio};
+use std::sync::Arc;
+use tracing::{error, info, warn};
 
-/// Generates an audio file from text using the Piper TTS engine.
+/// Default Piper model used when neither the CLI nor the config names one.
+pub const DEFAULT_PIPER_MODEL: &str = "./tts/en_US-hfc_male-medium.onnx";
+
+/// Default number of chunks synthesized concurrently.
+pub const DEFAULT_TTS_JOBS: usize = 4;
+
+/// Default number of extra attempts per chunk after a failed synthesis.
+pub const DEFAULT_TTS_RETRIES: usize = 2;
+
+/// How many times a repeatedly failing chunk may be split in half.
+const MAX_SPLIT_DEPTH: usize = 2;
+
+/// Subtitle colour for dialogue voices whose profile does not set one.
+pub const DEFAULT_DIALOGUE_COLOUR: &str = "#FFD54F";
+
+/// Default eSpeak NG voice.
+pub const DEFAULT_ESPEAK_VOICE: &str = "en-us";
+
+/// Available TTS backends.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum TtsEngineKind {
+    /// Piper neural TTS (`piper` binary)
+    Piper,
+    /// eSpeak NG formant synthesizer (`espeak-ng` binary)
+    Espeak,
+    /// Any command line described by a template
+    Command,
+}
+
+/// Timing of a single spoken word, relative to the start of its WAV file.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct WordTiming {
+    /// The word as spoken
+    pub word: String,
+    /// Start time in seconds
+    pub start: f64,
+    /// End time in seconds
+    pub end: f64,
+}
+
+/// A synthesized chunk of narration ready for subtitle and audio processing.
+#[derive(Debug, Clone)]
+pub struct SynthesizedChunk {
+    /// Path to the generated WAV file
+    pub path: String,
+    /// Text that was spoken
+    pub text: String,
+    /// Word timings reported by the engine, if it provides them
+    pub timings: Option<Vec<WordTiming>>,
+    /// Voice profile that spoke this chunk, or `None` for the narrator
+    pub voice: Option<String>,
+    /// Subtitle colour of the voice that spoke this chunk, as `#RRGGBB`
+    pub colour: Option<String>,
+    /// Whether this chunk is part of the post title
+    pub is_title: bool,
+}
+
+/// Errors produced while synthesizing a chunk.
+#[derive(Debug)]
+pub enum TtsError {
+    /// The engine executable could not be found
+    BinaryNotFound(String),
+    /// The voice model file does not exist
+    ModelMissing(String),
+    /// The engine exited with a non-zero status
+    NonZeroExit {
+        /// Program that failed
+        program: String,
+        /// Exit status it returned
+        status: ExitStatus,
+    },
+    /// The engine wrote no WAV file, an empty one, or one that cannot be parsed
+    InvalidOutput {
+        /// Path of the expected WAV file
+        path: String,
+        /// What was wrong with it
+        reason: String,
+    },
+    /// Any other I/O failure while talking to the engine
+    Io(std::io::Error),
+}
+
+impl TtsError {
+    /// Whether retrying the same chunk could succeed.
+    ///
+    /// A missing binary or model fails the same way every time, so those
+    /// errors abort immediately instead of being retried.
+    pub fn is_retryable(&self) -> bool {
+        !matches!(
+            self,
+            TtsError::BinaryNotFound(_) | TtsError::ModelMissing(_)
+        )
+    }
+
+    /// Classifies an error returned when spawning `program`.
+    pub fn from_spawn(program: &str, e: std::io::Error) -> Self {
+        if e.kind() == std::io::ErrorKind::NotFound {
+            TtsError::BinaryNotFound(program.to_string())
+        } else {
+            TtsError::Io(e)
+        }
+    }
+}
+
+impl fmt::Display for TtsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TtsError::BinaryNotFound(program) => {
+                write!(
+                    f,
+                    "TTS binary '{program}' not found; is it installed and in PATH?"
+                )
+            }
+            TtsError::ModelMissing(model) => write!(f, "TTS model not found: {model}"),
+            TtsError::NonZeroExit { program, status } => {
+                write!(f, "TTS command '{program}' failed with {status}")
+            }
+            TtsError::InvalidOutput { path, reason } => {
+                write!(f, "TTS produced invalid WAV {path}: {reason}")
+            }
+            TtsError::Io(e) => write!(f, "TTS I/O error: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for TtsError {}
+
+impl From<std::io::Error> for TtsError {
+    fn from(e: std::io::Error) -> Self {
+        TtsError::Io(e)
+    }
+}
+
+/// A text-to-speech backend that turns text into a WAV file.
+pub trait TtsEngine: Send + Sync {
+    /// Short identifier of the engine, used in logs.
+    fn name(&self) -> &str;
+
+    /// Identifies everything besides the text that affects the generated audio
+    /// (engine, model, voice parameters), used to key the TTS cache.
+    fn cache_key(&self) -> String;
+
+    /// Whether the engine understands inline `[[phonemes]]` in its input text.
+    fn supports_phonemes(&self) -> bool {
+        false
+    }
+
+    /// Synthesizes `text` into a WAV file at `out_path`.
+    ///
+    /// # Arguments
+    /// * `text` - Text content to convert to speech
+    /// * `out_path` - Output path for the generated WAV file
+    ///
+    /// # Returns
+    /// * `Ok(Some(timings))` - If the engine reports word-level timings
+    /// * `Ok(None)` - If the audio was generated without timing information
+    /// * `Err` - If synthesis fails
+    fn synthesize(&self, text: &str, out_path: &str) -> Result<Option<Vec<WordTiming>>, TtsError>;
+}
+
+/// Synthesizes a chunk, retrying failures and re-splitting it if it keeps failing.
 ///
-/// This function spawns a Piper process to convert the provided text into
-/// a WAV audio file using the specified voice model.
+/// Each attempt's output is checked to be a non-empty, readable WAV file. After
+/// `retries` failed retries the chunk is split in two near a sentence or clause
+/// boundary, both halves are synthesized the same way, and the results are
+/// joined back into `out_path`.
 ///
 /// # Arguments
-/// * `model` - Path to the Piper TTS model file (.onnx format)
+/// * `engine` - Engine used for synthesis
 /// * `text` - Text content to convert to speech
 /// * `out_path` - Output path for the generated WAV file
+/// * `retries` - Extra attempts after the first failure
 ///
 /// # Returns
-/// * `Ok(())` - If the audio file was successfully generated
-/// * `Err` - If the Piper process fails or cannot be spawned
-pub fn tts_generate_chunk(model: &str, text: &str, out_path: &str) -> anyhow::Result<()> {
-    let mut child = Command::new("piper")
-        .args(["--model", model, "--output_file", out_path])
+/// * `Ok(timings)` - The chunk was synthesized, with timings if the engine provides them
+/// * `Err(TtsError)` - The last error if every attempt failed
+pub fn synthesize_with_retry(
+    engine: &dyn TtsEngine,
+    text: &str,
+    out_path: &str,
+    retries: usize,
+) -> Result<Option<Vec<WordTiming>>, TtsError> {
+    synthesize_split(engine, text, out_path, retries, MAX_SPLIT_DEPTH)
+}
+
+fn synthesize_split(
+    engine: &dyn TtsEngine,
+    text: &str,
+    out_path: &str,
+    retries: usize,
+    depth: usize,
+) -> Result<Option<Vec<WordTiming>>, TtsError> {
+    let mut last_error = None;
+    for attempt in 1..=retries + 1 {
+        let result = engine
+            .synthesize(text, out_path)
+            .and_then(|timings| validate_wav(out_path).map(|_| timings));
+        match result {
+            Ok(timings) => return Ok(timings),
+            Err(e) if !e.is_retryable() => return Err(e),
+            Err(e) => {
+                warn!(
+                    "TTS attempt {}/{} for {} failed: {}",
+                    attempt,
+                    retries + 1,
+                    out_path,
+                    e
+                );
+                last_error = Some(e);
+            }
+        }
+    }
+    let error = last_error.expect("at least one attempt is made");
+
+    let Some((first, second)) = split_text(text).filter(|_| depth > 0) else {
+        return Err(error);
+    };
+    warn!(
+        "Re-splitting {} into two parts after repeated failures",
+        out_path
+    );
+    let stem = out_path.strip_suffix(".wav").unwrap_or(out_path);
+    let first_path = format!("{stem}_a.wav");
+    let second_path = format!("{stem}_b.wav");
+    let first_timings = synthesize_split(engine, &first, &first_path, retries, depth - 1)?;
+    let second_timings = synthesize_split(engine, &second, &second_path, retries, depth - 1)?;
+
+    let invalid = |e: anyhow::Error| TtsError::InvalidOutput {
+        path: out_path.to_string(),
+        reason: e.to_string(),
+    };
+    let offset = crate::audio::wav_duration_seconds(&first_path).map_err(invalid)?;
+    crate::audio::concat_wavs(&[first_path.clone(), second_path.clone()], out_path, 0.0)
+        .map_err(invalid)?;
+    let _ = fs::remove_file(&first_path);
+    let _ = fs::remove_file(&second_path);
+
+    Ok(first_timings
+        .zip(second_timings)
+        .map(|(mut first, second)| {
+            first.extend(second.into_iter().map(|t| WordTiming {
+                start: t.start + offset,
+                end: t.end + offset,
+                ..t
+            }));
+            first
+        }))
+}
+
+/// Checks that `path` is a readable WAV file containing at least one sample.
+pub fn validate_wav(path: &str) -> Result<(), TtsError> {
+    let invalid = |reason: String| TtsError::InvalidOutput {
+        path: path.to_string(),
+        reason,
+    };
+    let reader = hound::WavReader::open(path).map_err(|e| invalid(e.to_string()))?;
+    if reader.len() == 0 {
+        return Err(invalid("file contains no samples".to_string()));
+    }
+    Ok(())
+}
+
+/// Splits text into words, keeping each inline `[[phonemes]]` block in one word.
+fn atomic_words(text: &str) -> Vec<String> {
+    let mut words: Vec<String> = Vec::new();
+    let mut open = false;
+    for word in text.split_whitespace() {
+        match words.last_mut() {
+            Some(last) if open => {
+                last.push(' ');
+                last.push_str(word);
+            }
+            _ => words.push(word.to_string()),
+        }
+        let last = words.last().map_or("", String::as_str);
+        open = last.rfind("[[").is_some_and(|i| !last[i..].contains("]]"));
+    }
+    words
+}
+
+/// Splits text into two halves at the word boundary closest to the middle,
+/// preferring sentence ends over clause breaks over plain spaces.
+///
+/// # Returns
+/// * `Some((first, second))` - The two halves
+/// * `None` - If the text has fewer than two words
+fn split_text(text: &str) -> Option<(String, String)> {
+    let words = atomic_words(text);
+    if words.len() < 2 {
+        return None;
+    }
+    let total: usize = words.iter().map(|w| w.len() + 1).sum();
+    let mut position = 0;
+    let mut boundaries = Vec::new();
+    for (i, word) in words[..words.len() - 1].iter().enumerate() {
+        position += word.len() + 1;
+        let rank = match word.chars().last() {
+            Some('.' | '!' | '?') => 0,
+            Some(',' | ';' | ':') => 1,
+            _ => 2,
+        };
+        boundaries.push((rank, position.abs_diff(total / 2), i + 1));
+    }
+    // Prefer the strongest break within the middle half, else the most central one.
+    let (_, _, split) = boundaries
+        .iter()
+        .filter(|(_, distance, _)| *distance <= total / 4)
+        .min()
+        .or_else(|| boundaries.iter().min_by_key(|(_, distance, _)| *distance))
+        .copied()?;
+    Some((words[..split].join(" "), words[split..].join(" ")))
+}
+
+/// Resolves how many chunks may be synthesized concurrently.
+///
+/// # Arguments
+/// * `args` - Parsed command-line arguments
+/// * `config` - TTS section of the configuration file
+///
+/// # Returns
+/// * `usize` - Concurrency limit, at least 1
+pub fn resolve_jobs(args: &Args, config: &TtsConfig) -> usize {
+    args.tts_jobs
+        .or(config.jobs)
+        .unwrap_or(DEFAULT_TTS_JOBS)
+        .max(1)
+}
+
+/// Resolves the voice profile for narration.
+///
+/// Starts from the profile named by `--voice-profile` (or `tts.profile` in the
+/// config) and overrides it with any voice options given on the command line.
+///
+/// # Arguments
+/// * `args` - Parsed command-line arguments
+/// * `config` - TTS section of the configuration file
+///
+/// # Returns
+/// * `Ok(VoiceProfile)` - The effective voice parameters
+/// * `Err` - If the named profile does not exist in the configuration file
+pub fn resolve_voice_profile(args: &Args, config: &TtsConfig) -> anyhow::Result<VoiceProfile> {
+    let mut profile = match args.voice_profile.as_ref().or(config.profile.as_ref()) {
+        Some(name) => config
+            .profiles
+            .get(name)
+            .cloned()
+            .ok_or_else(|| anyhow::anyhow!("Unknown voice profile: {name}"))?,
+        None => VoiceProfile::default(),
+    };
+    if args.piper_model.is_some() {
+        profile.piper_model = args.piper_model.clone();
+    }
+    if args.speaker.is_some() {
+        profile.speaker = args.speaker.clone();
+    }
+    profile.length_scale = args.length_scale.or(profile.length_scale);
+    profile.noise_scale = args.noise_scale.or(profile.noise_scale);
+    profile.noise_w = args.noise_w.or(profile.noise_w);
+    profile.sentence_silence = args.sentence_silence.or(profile.sentence_silence);
+    Ok(profile)
+}
+
+/// Builds the TTS engine selected by the command line and configuration file.
+///
+/// Command-line values take precedence over the configuration file; Piper is
+/// used when neither selects an engine. The voice profile only applies to the
+/// Piper engine.
+///
+/// # Arguments
+/// * `args` - Parsed command-line arguments
+/// * `config` - TTS section of the configuration file
+/// * `profile` - Voice parameters for this engine
+///
+/// # Returns
+/// * `Ok(Box<dyn TtsEngine>)` - The configured engine
+/// * `Err` - If the selected engine is missing required settings or the voice
+///   parameters are invalid for the model
+pub fn build_engine(
+    args: &Args,
+    config: &TtsConfig,
+    profile: &VoiceProfile,
+) -> anyhow::Result<Box<dyn TtsEngine>> {
+    let engine: Box<dyn TtsEngine> = match engine_kind(args, config) {
+        TtsEngineKind::Piper => {
+            let model = profile
+                .piper_model
+                .clone()
+                .or_else(|| config.piper_model.clone())
+                .unwrap_or_else(|| DEFAULT_PIPER_MODEL.to_string());
+            let voice = PiperVoice::resolve(
+                model,
+                profile,
+                args.piper_phonemes.or(config.piper_phonemes),
+            )?;
+            Box::new(PiperEngine::new(voice, resolve_jobs(args, config)))
+        }
+        TtsEngineKind::Espeak => {
+            let voice = args
+                .espeak_voice
+                .clone()
+                .or_else(|| config.espeak_voice.clone())
+                .unwrap_or_else(|| DEFAULT_ESPEAK_VOICE.to_string());
+            Box::new(EspeakEngine { voice })
+        }
+        TtsEngineKind::Command => {
+            let template = args
+                .tts_command
+                .clone()
+                .or_else(|| config.command.clone())
+                .ok_or_else(|| {
+                    anyhow::anyhow!("The command TTS engine requires --tts-command or tts.command")
+                })?;
+            Box::new(CommandEngine::new(&template)?)
+        }
+    };
+    info!("Using TTS engine: {}", engine.name());
+    Ok(engine)
+}
+
+/// Returns the engine kind selected by the command line or configuration file.
+fn engine_kind(args: &Args, config: &TtsConfig) -> TtsEngineKind {
+    args.tts_engine
+        .or(config.engine)
+        .unwrap_or(TtsEngineKind::Piper)
+}
+
+/// An engine together with the subtitle colour of its voice.
+pub struct Voice {
+    /// Engine speaking with this voice
+    pub engine: Arc<dyn TtsEngine>,
+    /// Subtitle colour for this voice, as `#RRGGBB`
+    pub colour: Option<String>,
+}
+
+/// The narrator voice plus any named voices used for dialogue.
+pub struct VoiceSet {
+    narrator: Voice,
+    named: HashMap<String, Voice>,
+}
+
+impl VoiceSet {
+    /// Looks up a voice by profile name; `None` or an unknown name gives the narrator.
+    pub fn get(&self, name: Option<&str>) -> &Voice {
+        name.and_then(|n| self.named.get(n))
+            .unwrap_or(&self.narrator)
+    }
+}
+
+/// Builds the narrator voice and one voice per named profile.
+///
+/// The narrator uses [`resolve_voice_profile`], so command-line voice options
+/// apply to it only; named voices use their configuration profiles unchanged.
+///
+/// # Arguments
+/// * `args` - Parsed command-line arguments
+/// * `config` - TTS section of the configuration file
+/// * `names` - Profile names of the additional voices
+/// * `cache_dir` - TTS cache directory, or `None` to disable caching
+///
+/// # Returns
+/// * `Ok(VoiceSet)` - All voices, ready for synthesis
+/// * `Err` - If a profile is unknown or an engine cannot be built
+pub fn build_voices(
+    args: &Args,
+    config: &TtsConfig,
+    names: &[String],
+    cache_dir: Option<&str>,
+) -> anyhow::Result<VoiceSet> {
+    let wrap = |engine: Box<dyn TtsEngine>| -> anyhow::Result<Arc<dyn TtsEngine>> {
+        Ok(match cache_dir {
+            Some(dir) => Arc::new(CachedEngine::new(engine, TtsCache::open(dir)?)),
+            None => Arc::from(engine),
+        })
+    };
+
+    let profile = resolve_voice_profile(args, config)?;
+    let narrator = Voice {
+        engine: wrap(build_engine(args, config, &profile)?)?,
+        colour: profile.subtitle_colour.clone(),
+    };
+
+    if !names.is_empty() && engine_kind(args, config) != TtsEngineKind::Piper {
+        warn!("Voice profiles only change the voice with the Piper engine");
+    }
+    let mut named = HashMap::new();
+    for name in names {
+        if named.contains_key(name) {
+            continue;
+        }
+        let profile = config
+            .profiles
+            .get(name)
+            .ok_or_else(|| anyhow::anyhow!("Unknown voice profile: {name}"))?;
+        info!("Preparing voice profile '{}'", name);
+        let voice = Voice {
+            engine: wrap(build_engine(args, config, profile)?)?,
+            colour: Some(
+                profile
+                    .subtitle_colour
+                    .clone()
+                    .unwrap_or_else(|| DEFAULT_DIALOGUE_COLOUR.to_string()),
+            ),
+        };
+        named.insert(name.clone(), voice);
+    }
+    Ok(VoiceSet { narrator, named })
+}
+
+/// Spawns `cmd`, writes `text` to its stdin and waits for it to finish.
+///
+/// # Arguments
+/// * `cmd` - Fully configured command to run
+/// * `text` - Text written to the process's standard input
+/// * `out_path` - Output path of the chunk, used for error messages
+///
+/// # Returns
+/// * `Ok(())` - If the process exited successfully
+/// * `Err(TtsError)` - If the process cannot be spawned or returns non-zero
+fn run_with_stdin(mut cmd: Command, text: &str, out_path: &str) -> Result<(), TtsError> {
+    let program = cmd.get_program().to_string_lossy().into_owned();
+    let mut child = cmd
         .stdin(Stdio::piped())
         .stdout(Stdio::null())
         .stderr(Stdio::inherit())
         .spawn()
-        .expect("Failed to spawn piper process");
+        .map_err(|e| TtsError::from_spawn(&program, e))?;
 
     let status = {
-        let result = {
-            let stdin = child.stdin.as_mut().expect("Failed to open stdin");
-            stdin.write_all(text.as_bytes())
+        let result = match child.stdin.as_mut() {
+            Some(stdin) => stdin.write_all(text.as_bytes()),
+            None => Err(std::io::Error::other("Failed to open stdin")),
         };
         let wait_result = child.wait();
         result?;
         wait_result?
     };
+    check_status(&program, status, out_path)
+}
+
+/// Converts a non-zero exit status into [`TtsError::NonZeroExit`].
+fn check_status(program: &str, status: ExitStatus, out_path: &str) -> Result<(), TtsError> {
     if !status.success() {
-        error!("Piper TTS command failed for chunk: {}", out_path);
-        anyhow::bail!("TTS engine failed for chunk, command returned non-zero");
+        error!("TTS command failed for chunk: {}", out_path);
+        return Err(TtsError::NonZeroExit {
+            program: program.to_string(),
+            status,
+        });
     }
     Ok(())
 }
+
+/// eSpeak NG formant synthesizer.
+pub struct EspeakEngine {
+    /// eSpeak NG voice name (e.g. `en-us`)
+    pub voice: String,
+}
+
+impl TtsEngine for EspeakEngine {
+    fn name(&self) -> &str {
+        "espeak-ng"
+    }
+
+    fn cache_key(&self) -> String {
+        format!("espeak-ng|{}", self.voice)
+    }
+
+    fn supports_phonemes(&self) -> bool {
+        true
+    }
+
+    fn synthesize(&self, text: &str, out_path: &str) -> Result<Option<Vec<WordTiming>>, TtsError> {
+        let mut cmd = Command::new("espeak-ng");
+        cmd.args(["--stdin", "-v", &self.voice, "-w", out_path]);
+        run_with_stdin(cmd, text, out_path)?;
+        Ok(None)
+    }
+}
+
+/// Generic engine running a user-supplied command template.
+///
+/// The template is split on whitespace into a program and its arguments, and
+/// the following placeholders are substituted in each argument:
+///
+/// * `{output}` - path of the WAV file to write
+/// * `{text}` - the text to speak, passed as a single argument
+/// * `{text_file}` - path of a UTF-8 file containing the text
+/// * `{timings}` - path where the command may write word timings as a JSON
+///   array of `{"word", "start", "end"}` objects
+///
+/// If neither `{text}` nor `{text_file}` is used, the text is written to the
+/// command's standard input.
+pub struct CommandEngine {
+    program: String,
+    args: Vec<String>,
+}
+
+impl CommandEngine {
+    /// Parses a command template.
+    ///
+    /// # Arguments
+    /// * `template` - Command line with placeholders, e.g. `mytts --out {output}`
+    ///
+    /// # Returns
+    /// * `Ok(CommandEngine)` - The parsed engine
+    /// * `Err` - If the template is empty or does not reference `{output}`
+    pub fn new(template: &str) -> anyhow::Result<Self> {
+        let mut parts = template.split_whitespace().map(str::to_string);
+        let program = parts
+            .next()
+            .ok_or_else(|| anyhow::anyhow!("TTS command template is empty"))?;
+        let args: Vec<String> = parts.collect();
+        if !args.iter().any(|a| a.contains("{output}")) {
+            anyhow::bail!("TTS command template must contain an {{output}} placeholder");
+        }
+        Ok(Self { program, args })
+    }
+
+    fn uses(&self, placeholder: &str) -> bool {
+        self.args.iter().any(|a| a.contains(placeholder))
+    }
+
+    /// Runs the command for one chunk and reads back any timings it wrote.
+    fn run(
+        &self,
+        text: &str,
+        out_path: &str,
+        text_file: &str,
+        timings_file: &str,
+    ) -> Result<Option<Vec<WordTiming>>, TtsError> {
+        if self.uses("{text_file}") {
+            fs::write(text_file, text)?;
+        }
+
+        let mut cmd = Command::new(&self.program);
+        for arg in &self.args {
+            cmd.arg(
+                arg.replace("{output}", out_path)
+                    .replace("{text_file}", text_file)
+                    .replace("{timings}", timings_file)
+                    .replace("{text}", text),
+            );
+        }
+        if self.uses("{text}") || self.uses("{text_file}") {
+            let status = cmd
+                .stdout(Stdio::null())
+                .stderr(Stdio::inherit())
+                .status()
+                .map_err(|e| TtsError::from_spawn(&self.program, e))?;
+            check_status(&self.program, status, out_path)?;
+        } else {
+            run_with_stdin(cmd, text, out_path)?;
+        }
+
+        if !self.uses("{timings}") {
+            return Ok(None);
+        }
+        match fs::read_to_string(timings_file) {
+            Ok(data) => {
+                serde_json::from_str(&data)
+                    .map(Some)
+                    .map_err(|e| TtsError::InvalidOutput {
+                        path: timings_file.to_string(),
+                        reason: e.to_string(),
+                    })
+            }
+            Err(_) => Ok(None),
+        }
+    }
+}
+
+impl TtsEngine for CommandEngine {
+    fn name(&self) -> &str {
+        &self.program
+    }
+
+    fn cache_key(&self) -> String {
+        format!("command|{}|{}", self.program, self.args.join(" "))
+    }
+
+    fn synthesize(&self, text: &str, out_path: &str) -> Result<Option<Vec<WordTiming>>, TtsError> {
+        let text_file = format!("{out_path}.txt");
+        let timings_file = format!("{out_path}.timings.json");
+        // Retries and re-splits reuse the output path, so timings from an
+        // earlier attempt must not be mistaken for this one's.
+        let _ = fs::remove_file(&timings_file);
+        let result = self.run(text, out_path, &text_file, &timings_file);
+        for side_file in [&text_file, &timings_file] {
+            let _ = fs::remove_file(side_file);
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::align::Alignment;
+    use crate::audio::SilenceOptions;
+    use crate::subtitle::build_track;
+    use std::path::Path;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Seconds of audio the fake engine produces per word.
+    const WORD_SECONDS: f64 = 0.25;
+    const SAMPLE_RATE: u32 = 16_000;
+
+    /// An engine that writes a tone per word and reports fixed word timings.
+    #[derive(Default)]
+    struct FakeEngine {
+        /// Number of calls that fail before the engine starts working
+        failures: usize,
+        /// Longest text, in words, the engine accepts
+        max_words: Option<usize>,
+        calls: AtomicUsize,
+    }
+
+    impl TtsEngine for FakeEngine {
+        fn name(&self) -> &str {
+            "fake"
+        }
+
+        fn cache_key(&self) -> String {
+            "fake".to_string()
+        }
+
+        fn synthesize(
+            &self,
+            text: &str,
+            out_path: &str,
+        ) -> Result<Option<Vec<WordTiming>>, TtsError> {
+            let call = self.calls.fetch_add(1, Ordering::SeqCst);
+            let words: Vec<&str> = text.split_whitespace().collect();
+            if call < self.failures || self.max_words.is_some_and(|max| words.len() > max) {
+                return Err(TtsError::Io(std::io::Error::other("fake failure")));
+            }
+            let spec = hound::WavSpec {
+                channels: 1,
+                sample_rate: SAMPLE_RATE,
+                bits_per_sample: 16,
+                sample_format: hound::SampleFormat::Int,
+            };
+            let mut writer =
+                hound::WavWriter::create(out_path, spec).map_err(|e| TtsError::InvalidOutput {
+                    path: out_path.to_string(),
+                    reason: e.to_string(),
+                })?;
+            let frames = (words.len() as f64 * WORD_SECONDS * SAMPLE_RATE as f64) as usize;
+            for n in 0..frames {
+                let t = n as f64 / SAMPLE_RATE as f64;
+                let sample = (t * 440.0 * std::f64::consts::TAU).sin() * 8000.0;
+                writer.write_sample(sample as i16).unwrap();
+            }
+            writer.finalize().unwrap();
+            Ok(Some(
+                words
+                    .iter()
+                    .enumerate()
+                    .map(|(i, word)| WordTiming {
+                        word: word.to_string(),
+                        start: i as f64 * WORD_SECONDS + 0.05,
+                        end: i as f64 * WORD_SECONDS + 0.2,
+                    })
+                    .collect(),
+            ))
+        }
+    }
+
+    fn temp_wav(name: &str) -> String {
+        std::env::temp_dir()
+            .join(format!("tts_test_{}_{name}.wav", std::process::id()))
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    #[test]
+    fn engine_timings_are_returned() {
+        let path = temp_wav("timings");
+        let timings = synthesize_with_retry(&FakeEngine::default(), "hello there", &path, 0)
+            .unwrap()
+            .unwrap();
+        assert_eq!(timings.len(), 2);
+        assert_eq!(timings[1].word, "there");
+        assert!((timings[1].start - 0.3).abs() < 1e-9);
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn failed_attempts_are_retried() {
+        let path = temp_wav("retry");
+        let engine = FakeEngine {
+            failures: 2,
+            ..FakeEngine::default()
+        };
+        let timings = synthesize_with_retry(&engine, "one two three", &path, 2)
+            .unwrap()
+            .unwrap();
+        assert_eq!(engine.calls.load(Ordering::SeqCst), 3);
+        assert_eq!(timings.len(), 3);
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn resplit_chunks_keep_timings_in_order() {
+        let path = temp_wav("resplit");
+        let engine = FakeEngine {
+            max_words: Some(2),
+            ..FakeEngine::default()
+        };
+        let timings = synthesize_with_retry(&engine, "one two. three four", &path, 0)
+            .unwrap()
+            .unwrap();
+        let words: Vec<&str> = timings.iter().map(|t| t.word.as_str()).collect();
+        assert_eq!(words, ["one", "two.", "three", "four"]);
+        // The second half is offset by the first half's two words of audio.
+        assert!((timings[2].start - (2.0 * WORD_SECONDS + 0.05)).abs() < 1e-3);
+        let duration = crate::audio::wav_duration_seconds(&path).unwrap();
+        assert!((duration - 4.0 * WORD_SECONDS).abs() < 1e-3);
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn track_uses_engine_timings() {
+        let engine = FakeEngine::default();
+        let mut chunks = Vec::new();
+        for (i, text) in ["Hello there.", "Bye now."].into_iter().enumerate() {
+            let path = temp_wav(&format!("track_{i}"));
+            let timings = synthesize_with_retry(&engine, text, &path, 0).unwrap();
+            chunks.push(SynthesizedChunk {
+                path,
+                text: text.to_string(),
+                timings,
+                voice: None,
+                colour: None,
+                is_title: false,
+            });
+        }
+        let gap = 0.5;
+        let track =
+            build_track(&chunks, gap, &SilenceOptions::default(), Alignment::Energy).unwrap();
+        let times: Vec<(f64, f64)> = track.cues.iter().map(|c| (c.start, c.end)).collect();
+        let second = 2.0 * WORD_SECONDS + gap;
+        let expected = [
+            (0.05, 0.2),
+            (0.3, 0.45),
+            (second + 0.05, second + 0.2),
+            (second + 0.3, second + 0.45),
+        ];
+        assert_eq!(times.len(), expected.len());
+        for ((start, end), (want_start, want_end)) in times.iter().zip(expected) {
+            assert!((start - want_start).abs() < 1e-3, "{times:?}");
+            assert!((end - want_end).abs() < 1e-3, "{times:?}");
+        }
+        for chunk in chunks {
+            fs::remove_file(chunk.path).unwrap();
+        }
+    }
+
+    #[test]
+    fn phoneme_blocks_are_not_split() {
+        let words = atomic_words("Hi [[ŋwˈɪn  ðə]] there [[a]] [[b c");
+        assert_eq!(words, ["Hi", "[[ŋwˈɪn ðə]]", "there", "[[a]]", "[[b c"]);
+        let (first, second) = split_text("say [[ŋwˈɪn ðə ɡɹˈeɪt]] now").unwrap();
+        assert!(
+            [&first, &second]
+                .iter()
+                .all(|half| half.matches("[[").count() == half.matches("]]").count()),
+            "{first:?} / {second:?}"
+        );
+    }
+
+    /// A command engine running a shell script that copies a tone to `{output}`.
+    ///
+    /// With `write_timings`, the script also writes one word timing to `{timings}`.
+    fn script_engine(name: &str, write_timings: bool) -> (CommandEngine, String, Vec<String>) {
+        let dir = std::env::temp_dir();
+        let id = std::process::id();
+        let tone = dir.join(format!("command_{name}_{id}_tone.wav"));
+        FakeEngine::default()
+            .synthesize("one", tone.to_str().unwrap())
+            .unwrap();
+        let script = dir.join(format!("command_{name}_{id}.sh"));
+        let mut body = format!("cp '{}' \"$1\"\n", tone.display());
+        if write_timings {
+            body.push_str(r#"echo '[{"word":"fresh","start":0.0,"end":0.1}]' > "$2""#);
+            body.push('\n');
+        }
+        fs::write(&script, body).unwrap();
+        let engine = CommandEngine::new(&format!(
+            "sh {} {{output}} {{timings}} {{text_file}}",
+            script.display()
+        ))
+        .unwrap();
+        let out = dir.join(format!("command_{name}_{id}.wav"));
+        let cleanup = vec![
+            tone.to_string_lossy().into_owned(),
+            script.to_string_lossy().into_owned(),
+            out.to_string_lossy().into_owned(),
+        ];
+        (engine, out.to_string_lossy().into_owned(), cleanup)
+    }
+
+    #[test]
+    fn stale_timings_are_not_returned() {
+        let (engine, out, cleanup) = script_engine("stale", false);
+        let stale = format!("{out}.timings.json");
+        fs::write(&stale, r#"[{"word":"stale","start":0.0,"end":0.1}]"#).unwrap();
+        let timings = engine.synthesize("one", &out).unwrap();
+        assert!(timings.is_none(), "{timings:?}");
+        assert!(!Path::new(&stale).exists());
+        for path in cleanup {
+            fs::remove_file(path).unwrap();
+        }
+    }
+
+    #[test]
+    fn command_side_files_are_removed() {
+        let (engine, out, cleanup) = script_engine("side_files", true);
+        let timings = engine.synthesize("one", &out).unwrap().unwrap();
+        assert_eq!(timings.len(), 1);
+        assert_eq!(timings[0].word, "fresh");
+        assert!(!Path::new(&format!("{out}.txt")).exists());
+        assert!(!Path::new(&format!("{out}.timings.json")).exists());
+        for path in cleanup {
+            fs::remove_file(path).unwrap();
+        }
+    }
+
+    #[test]
+    fn command_side_files_are_removed_on_failure() {
+        let dir = std::env::temp_dir();
+        let out = dir
+            .join(format!("command_failure_{}.wav", std::process::id()))
+            .to_string_lossy()
+            .into_owned();
+        let engine = CommandEngine::new("false {output} {timings} {text_file}").unwrap();
+        assert!(engine.synthesize("one", &out).is_err());
+        assert!(!Path::new(&format!("{out}.txt")).exists());
+        assert!(!Path::new(&format!("{out}.timings.json")).exists());
+    }
+}
@@ -8,6 +8,7 @@ use reqwest::Client;
 use serde_json::Value;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
+use std::sync::LazyLock;
 use tracing::warn;
 
 /// Splits text into chunks based on sentence boundaries and character limits.
@@ -54,6 +55,183 @@ pub fn chunk_text(text: &str, max_chars: usize) -> Vec<String> {
     chunks
 }
 
+/// Whether a stretch of story text is narration or quoted speech.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SegmentKind {
+    /// Narrator text outside quotes
+    Narration,
+    /// Quoted speech, with the attributed speaker if one was found (e.g. `sister`)
+    Dialogue { speaker: Option<String> },
+}
+
+/// A contiguous stretch of story text of a single [`SegmentKind`].
+#[derive(Debug, Clone)]
+pub struct TextSegment {
+    /// Segment text, without surrounding quote marks
+    pub text: String,
+    /// Narration or dialogue
+    pub kind: SegmentKind,
+}
+
+/// A TTS chunk together with the voice that should speak it.
+#[derive(Debug, Clone)]
+pub struct VoicedChunk {
+    /// Chunk text
+    pub text: String,
+    /// Voice profile name, or `None` for the narrator
+    pub voice: Option<String>,
+}
+
+/// Verbs that attribute quoted speech to a speaker.
+const SPEECH_VERBS: &str = "said|says|asked|asks|yelled|yells|screamed|screams|shouted|shouts|replied|replies|whispered|whispers|told me|tells me|texted|texts|snapped|added|goes";
+
+static SPEAKER_BEFORE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(&format!(
+        r"(?i)\b(?:(?:my|her|his|our|their|the)\s+)?([a-z]+)\s+(?:{SPEECH_VERBS})(?:\s+to\s+\w+)?\s*[,:]?\s*$"
+    ))
+    .unwrap()
+});
+
+static SPEAKER_AFTER: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(&format!(
+        r"(?i)^\s*[,.!?]?\s*(?:(?:{SPEECH_VERBS})\s+(?:(?:my|her|his|our|their|the)\s+)?([a-z]+)|(?:(?:my|her|his|our|their|the)\s+)?([a-z]+)\s+(?:{SPEECH_VERBS}))\b"
+    ))
+    .unwrap()
+});
+
+/// Splits story text into narration and quoted dialogue segments.
+///
+/// Double quotes always delimit dialogue. Single quotes only do when they open
+/// after a non-letter and close before one, so apostrophes such as "you're"
+/// are left alone. Speakers are attributed from phrases like "my sister said"
+/// directly before or after the quote; pronouns are not attributed.
+///
+/// # Arguments
+/// * `text` - Story text to segment
+///
+/// # Returns
+/// * `Vec<TextSegment>` - Segments in story order
+pub fn split_dialogue(text: &str) -> Vec<TextSegment> {
+    let chars: Vec<(usize, char)> = text.char_indices().collect();
+    let mut spans = Vec::new();
+    let mut open: Option<(usize, char)> = None;
+    for (k, &(i, c)) in chars.iter().enumerate() {
+        let prev = k.checked_sub(1).map(|p| chars[p].1);
+        let next = chars.get(k + 1).map(|&(_, n)| n);
+        match open {
+            None if c == '"' => open = Some((i, c)),
+            None if c == '\''
+                && prev.is_none_or(|p| !p.is_alphanumeric())
+                && next.is_some_and(|n| n.is_alphanumeric()) =>
+            {
+                open = Some((i, c));
+            }
+            Some((start, quote))
+                if c == quote && (quote == '"' || next.is_none_or(|n| !n.is_alphanumeric())) =>
+            {
+                spans.push((start, i));
+                open = None;
+            }
+            _ => {}
+        }
+    }
+
+    let mut segments = Vec::new();
+    let mut cursor = 0;
+    for (k, &(start, end)) in spans.iter().enumerate() {
+        let before = &text[cursor..start];
+        let next_start = spans.get(k + 1).map_or(text.len(), |&(s, _)| s);
+        let after = &text[end + 1..next_start];
+        if has_words(before) {
+            segments.push(TextSegment {
+                text: before.trim().to_string(),
+                kind: SegmentKind::Narration,
+            });
+        }
+        let quoted = text[start + 1..end].trim();
+        if has_words(quoted) {
+            segments.push(TextSegment {
+                text: quoted.to_string(),
+                kind: SegmentKind::Dialogue {
+                    speaker: attribute_speaker(before, after),
+                },
+            });
+        }
+        cursor = end + 1;
+    }
+    if has_words(&text[cursor..]) {
+        segments.push(TextSegment {
+            text: text[cursor..].trim().to_string(),
+            kind: SegmentKind::Narration,
+        });
+    }
+    segments
+}
+
+/// Whether `text` contains anything speakable, as opposed to bare punctuation.
+fn has_words(text: &str) -> bool {
+    text.chars().any(char::is_alphanumeric)
+}
+
+/// Finds who spoke a quote from the narration around it.
+///
+/// # Arguments
+/// * `before` - Narration directly preceding the quote
+/// * `after` - Narration directly following the quote
+///
+/// # Returns
+/// * `Some(String)` - Lowercase speaker noun or name (e.g. `mil`, `jake`)
+/// * `None` - If no non-pronoun speaker was found
+fn attribute_speaker(before: &str, after: &str) -> Option<String> {
+    const PRONOUNS: [&str; 9] = ["i", "he", "she", "they", "we", "you", "it", "and", "then"];
+    let from_before = SPEAKER_BEFORE
+        .captures(before)
+        .and_then(|c| c.get(1))
+        .map(|m| m.as_str());
+    let from_after = SPEAKER_AFTER
+        .captures(after)
+        .and_then(|c| c.get(1).or_else(|| c.get(2)))
+        .map(|m| m.as_str());
+    from_before
+        .into_iter()
+        .chain(from_after)
+        .map(str::to_lowercase)
+        .find(|w| !PRONOUNS.contains(&w.as_str()))
+}
+
+/// Chunks story text for multi-voice narration.
+///
+/// Narration and dialogue are chunked separately with [`chunk_text`], so a
+/// chunk never mixes voices.
+///
+/// # Arguments
+/// * `text` - The input text to be chunked
+/// * `max_chars` - Maximum characters allowed per chunk
+/// * `pick_voice` - Maps a dialogue segment's attributed speaker to a voice profile name
+///
+/// # Returns
+/// * `Vec<VoicedChunk>` - Chunks in story order, tagged with their voice
+pub fn chunk_dialogue(
+    text: &str,
+    max_chars: usize,
+    pick_voice: impl Fn(Option<&str>) -> String,
+) -> Vec<VoicedChunk> {
+    let mut chunks = Vec::new();
+    for segment in split_dialogue(text) {
+        let voice = match &segment.kind {
+            SegmentKind::Narration => None,
+            SegmentKind::Dialogue { speaker } => Some(pick_voice(speaker.as_deref())),
+        };
+        for chunk in chunk_text(&segment.text, max_chars) {
+            chunks.push(VoicedChunk {
+                text: chunk,
+                voice: voice.clone(),
+            });
+        }
+    }
+    chunks
+}
+
 /// Loads a list of forbidden words from a text file for content filtering.
 ///
 /// # Arguments
@@ -146,3 +324,100 @@ pub async fn correct_grammar(text: &str) -> Option<String> {
     }
     Some(corrected)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Flattens segments into `(text, speaker)` pairs: `None` for narration, `Some(speaker)` for dialogue.
+    fn dialogue(text: &str) -> Vec<(String, Option<Option<String>>)> {
+        split_dialogue(text)
+            .into_iter()
+            .map(|s| match s.kind {
+                SegmentKind::Narration => (s.text, None),
+                SegmentKind::Dialogue { speaker } => (s.text, Some(speaker)),
+            })
+            .collect()
+    }
+
+    fn said(text: &str, speaker: Option<&str>) -> (String, Option<Option<String>>) {
+        (text.to_string(), Some(speaker.map(str::to_string)))
+    }
+
+    fn narrated(text: &str) -> (String, Option<Option<String>>) {
+        (text.to_string(), None)
+    }
+
+    #[test]
+    fn apostrophes_inside_quotes_do_not_end_them() {
+        assert_eq!(
+            dialogue(r#""I don't know," she said."#),
+            [said("I don't know,", None), narrated("she said.")]
+        );
+        assert_eq!(
+            dialogue("My MIL said, 'you're ruining Christmas' and left."),
+            [
+                narrated("My MIL said,"),
+                said("you're ruining Christmas", Some("mil")),
+                narrated("and left."),
+            ]
+        );
+    }
+
+    #[test]
+    fn speakers_are_attributed_before_and_after_quotes() {
+        assert_eq!(
+            dialogue(r#"Then my MIL said "You are late." We ignored her."#),
+            [
+                narrated("Then my MIL said"),
+                said("You are late.", Some("mil")),
+                narrated("We ignored her."),
+            ]
+        );
+        assert_eq!(
+            dialogue(r#""Get out," yelled Jake. "Now," my sister added."#),
+            [
+                said("Get out,", Some("jake")),
+                narrated("yelled Jake."),
+                said("Now,", Some("sister")),
+                narrated("my sister added."),
+            ]
+        );
+    }
+
+    #[test]
+    fn unattributed_quotes_have_no_speaker() {
+        assert_eq!(
+            dialogue(r#"The sign read "Closed." He shrugged and said "Fine.""#),
+            [
+                narrated("The sign read"),
+                said("Closed.", None),
+                narrated("He shrugged and said"),
+                said("Fine.", None),
+            ]
+        );
+    }
+
+    #[test]
+    fn dialogue_chunks_keep_their_voice() {
+        let text = r#"I got home late. "Where were you?" my mom asked. "Out," I said. Then I went to bed."#;
+        let chunks = chunk_dialogue(text, 200, |speaker| match speaker {
+            Some("mom") => "mom_voice".to_string(),
+            _ => "dialogue_voice".to_string(),
+        });
+        let voiced: Vec<(&str, Option<&str>)> = chunks
+            .iter()
+            .map(|c| (c.text.as_str(), c.voice.as_deref()))
+            .collect();
+        assert_eq!(
+            voiced,
+            [
+                ("I got home late.", None),
+                ("Where were you?", Some("mom_voice")),
+                ("my mom asked.", None),
+                ("Out,", Some("dialogue_voice")),
+                ("I said. Then I went to bed.", None),
+            ]
+        );
+    }
+}